@@ -1,3 +1,22 @@
+/// Models whose license doesn't allow redistributing them with the app. Everything in `models/`
+/// is bundled, so these must be installed into the app data directory instead.
+const UNBUNDLED_MODELS: &[&str] = &["w600k_mbf.onnx"];
+
 fn main() {
+    for file_name in UNBUNDLED_MODELS {
+        let path = std::path::Path::new("models").join(file_name);
+        println!("cargo:rerun-if-changed={}", path.display());
+        if !path.exists() {
+            continue;
+        }
+        let message = format!(
+            "{} must not be bundled; move it to the app data `models` directory or point PICKSY_MODELS_DIR at it (see models/README.md)",
+            path.display()
+        );
+        if std::env::var("PROFILE").as_deref() == Ok("release") {
+            panic!("{message}");
+        }
+        println!("cargo:warning={message}");
+    }
     tauri_build::build()
 }
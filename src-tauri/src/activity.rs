@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// What an activity log entry records. The entry's targets say which photos, albums, comments
+/// etc. were affected. Data the background indexers derive (faces, machine tags, embeddings)
+/// isn't logged; only changes someone made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    ImportPhotos,
+    CreateVirtualCopy,
+    RemovePhoto,
+    ClearLibrary,
+    EditConfig,
+    SetFavorite,
+    SetRating,
+    SetColorLabels,
+    SetRejected,
+    SetStack,
+    SetStackPrimary,
+    ClearStack,
+    DismissStackSuggestion,
+    EditCaptureTime,
+    EditLocation,
+    AddTags,
+    RemoveTags,
+    RenameTag,
+    ScoreQuality,
+    Vote,
+    AddComment,
+    EditComment,
+    DeleteComment,
+    AddAnnotation,
+    SetAnnotationStatus,
+    DeleteAnnotation,
+    CreateAlbum,
+    UpdateAlbum,
+    SetAlbumVisibility,
+    DeleteAlbum,
+    AddToAlbum,
+    RemoveFromAlbum,
+    ReorderAlbum,
+    SaveSmartAlbum,
+    DeleteSmartAlbum,
+    CreateCullSession,
+    DeleteCullSession,
+    RecordCullChoice,
+    NamePerson,
+    ConfirmFaces,
+    MergePeople,
+    SplitFaces,
+    RejectFaces,
+    /// Logged by a newer peer with an action this build doesn't know yet.
+    #[serde(other)]
+    Other,
+}
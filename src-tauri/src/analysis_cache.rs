@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Per-photo analysis results, keyed by content ID (the photo `_id`), stored next to the Ditto root.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join("analysis_cache");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create analysis cache dir: {e}"))?;
+    let _ = CACHE_DIR.set(dir);
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry<T> {
+    source_len: Option<u64>,
+    source_modified: Option<u64>,
+    value: T,
+}
+
+/// Size and mtime of the original; `None` when it isn't on this device.
+pub fn fingerprint(source_path: &str) -> (Option<u64>, Option<u64>) {
+    let Ok(metadata) = std::fs::metadata(source_path) else {
+        return (None, None);
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    (Some(metadata.len()), modified)
+}
+
+fn entry_path(kind: &str, content_id: &str) -> Option<PathBuf> {
+    let file_name: String = content_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    CACHE_DIR
+        .get()
+        .map(|dir| dir.join(kind).join(format!("{file_name}.json")))
+}
+
+/// Returns the cached value unless the original on disk changed since it was computed.
+pub fn get<T: DeserializeOwned>(kind: &str, content_id: &str, source_path: &str) -> Option<T> {
+    let path = entry_path(kind, content_id)?;
+    let bytes = std::fs::read(&path).ok()?;
+    let entry: CacheEntry<T> = serde_json::from_slice(&bytes).ok()?;
+    let (len, modified) = fingerprint(source_path);
+    let changed = len.is_some() && (entry.source_len != len || entry.source_modified != modified);
+    if changed {
+        let _ = std::fs::remove_file(&path);
+        return None;
+    }
+    Some(entry.value)
+}
+
+pub fn put<T: Serialize>(kind: &str, content_id: &str, source_path: &str, value: &T) -> Result<(), String> {
+    let Some(path) = entry_path(kind, content_id) else {
+        return Err("Analysis cache not initialized".to_string());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let (source_len, source_modified) = fingerprint(source_path);
+    let entry = CacheEntry {
+        source_len,
+        source_modified,
+        value,
+    };
+    let bytes = serde_json::to_vec(&entry).map_err(|e| e.to_string())?;
+    write_atomic(&path, &bytes)
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write cache entry: {e}"))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write cache entry: {e}"))
+}
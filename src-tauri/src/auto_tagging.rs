@@ -0,0 +1,89 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+/// Scene and object vocabulary, scored zero-shot against the CLIP image embedding.
+const LABELS: &[(&str, &str)] = &[
+    ("beach", "a photo of a beach"),
+    ("mountains", "a photo of mountains"),
+    ("forest", "a photo of a forest"),
+    ("city", "a photo of a city street"),
+    ("indoor", "a photo taken indoors"),
+    ("night", "a photo taken at night"),
+    ("sunset", "a photo of a sunset"),
+    ("snow", "a photo of snow"),
+    ("water", "a photo of a lake or the sea"),
+    ("food", "a photo of food"),
+    ("drink", "a photo of a drink"),
+    ("portrait", "a portrait photo of a person"),
+    ("group", "a group photo of people"),
+    ("crowd", "a photo of a crowd"),
+    ("child", "a photo of a child"),
+    ("wedding", "a photo of a wedding"),
+    ("party", "a photo of a party"),
+    ("sports", "a photo of people playing sports"),
+    ("concert", "a photo of a concert"),
+    ("dog", "a photo of a dog"),
+    ("cat", "a photo of a cat"),
+    ("bird", "a photo of a bird"),
+    ("flowers", "a photo of flowers"),
+    ("car", "a photo of a car"),
+    ("architecture", "a photo of a building"),
+    ("document", "a photo of a document with text"),
+    ("screenshot", "a screenshot of a computer screen"),
+    ("art", "a photo of a painting or artwork"),
+];
+/// CLIP's learned logit scale.
+const LOGIT_SCALE: f32 = 100.0;
+const MIN_CONFIDENCE: f32 = 0.15;
+const MAX_TAGS: usize = 5;
+
+/// A tag assigned by the classifier, kept apart from the tags people add by hand.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineTag {
+    pub tag: String,
+    pub confidence: f32,
+}
+
+static LABEL_EMBEDDINGS: OnceLock<Result<Vec<Vec<f32>>, String>> = OnceLock::new();
+
+pub const fn enabled() -> bool {
+    cfg!(feature = "auto_tagging")
+}
+
+fn label_embeddings() -> Result<&'static Vec<Vec<f32>>, String> {
+    LABEL_EMBEDDINGS
+        .get_or_init(|| {
+            LABELS
+                .iter()
+                .map(|(_, prompt)| crate::semantic_search::embed_text(prompt))
+                .collect()
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+/// Softmax over the vocabulary; only confident labels are kept, most confident first.
+pub fn classify(image_embedding: &[f32]) -> Result<Vec<MachineTag>, String> {
+    let labels = label_embeddings()?;
+    let logits: Vec<f32> = labels
+        .iter()
+        .map(|label| LOGIT_SCALE * crate::faces::cosine_similarity(image_embedding, label))
+        .collect();
+    let max = logits.iter().copied().fold(f32::MIN, f32::max);
+    let exp: Vec<f32> = logits.iter().map(|logit| (logit - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+
+    let mut tags: Vec<MachineTag> = LABELS
+        .iter()
+        .zip(exp)
+        .map(|((tag, _), value)| MachineTag {
+            tag: tag.to_string(),
+            confidence: value / sum,
+        })
+        .filter(|tag| tag.confidence >= MIN_CONFIDENCE)
+        .collect();
+    tags.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    tags.truncate(MAX_TAGS);
+    Ok(tags)
+}
@@ -0,0 +1,181 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::ditto_repo::PhotoPayload;
+
+const SAMPLE_SIZE: u32 = 64;
+const PALETTE_SIZE: usize = 5;
+const KMEANS_ITERATIONS: usize = 10;
+/// Colors covering less of the frame than this don't count as "the photo's color" in searches.
+const MIN_SEARCH_WEIGHT: f32 = 0.05;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaletteColor {
+    /// `#rrggbb`
+    pub hex: String,
+    /// Share of the thumbnail's pixels closest to this color, `0.0..=1.0`.
+    pub weight: f32,
+}
+
+/// Dominant colors, heaviest first, clustered with k-means in CIE Lab.
+pub fn extract_palette(img: &DynamicImage) -> Vec<PaletteColor> {
+    let rgb = img
+        .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgb8();
+    let pixels: Vec<[f32; 3]> = rgb.pixels().map(|p| rgb_to_lab([p[0], p[1], p[2]])).collect();
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut centroids = initial_centroids(&pixels, PALETTE_SIZE);
+    let mut counts = vec![0usize; centroids.len()];
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![[0.0f32; 3]; centroids.len()];
+        counts.iter_mut().for_each(|count| *count = 0);
+        for pixel in &pixels {
+            let nearest = nearest_centroid(&centroids, pixel);
+            for c in 0..3 {
+                sums[nearest][c] += pixel[c];
+            }
+            counts[nearest] += 1;
+        }
+        for (idx, centroid) in centroids.iter_mut().enumerate() {
+            if counts[idx] > 0 {
+                *centroid = sums[idx].map(|sum| sum / counts[idx] as f32);
+            }
+        }
+    }
+
+    let mut palette: Vec<PaletteColor> = centroids
+        .iter()
+        .zip(&counts)
+        .filter(|(_, count)| **count > 0)
+        .map(|(lab, count)| PaletteColor {
+            hex: rgb_to_hex(lab_to_rgb(*lab)),
+            weight: *count as f32 / pixels.len() as f32,
+        })
+        .collect();
+    palette.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    palette
+}
+
+/// Stored palette if there is one, otherwise extracted from the thumbnail.
+pub fn photo_palette(photo: &PhotoPayload) -> Vec<PaletteColor> {
+    if !photo.palette.is_empty() {
+        return photo.palette.clone();
+    }
+    crate::thumbnails::decode_thumbnail(&photo.base64)
+        .map(|img| extract_palette(&img))
+        .unwrap_or_default()
+}
+
+/// CIE76 delta E between `hex` and the closest significant palette color.
+pub fn color_distance(palette: &[PaletteColor], hex: &str) -> Option<f32> {
+    let target = rgb_to_lab(parse_hex(hex)?);
+    palette
+        .iter()
+        .filter(|color| color.weight >= MIN_SEARCH_WEIGHT)
+        .filter_map(|color| Some(delta_e(&target, &rgb_to_lab(parse_hex(&color.hex)?))))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+pub fn parse_hex(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn rgb_to_hex(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Spreads seeds by picking, each time, the pixel farthest from the seeds so far (deterministic k-means++).
+fn initial_centroids(pixels: &[[f32; 3]], k: usize) -> Vec<[f32; 3]> {
+    let mut centroids = vec![pixels[pixels.len() / 2]];
+    while centroids.len() < k {
+        let farthest = pixels
+            .iter()
+            .map(|pixel| {
+                let nearest = centroids
+                    .iter()
+                    .map(|c| delta_e(c, pixel))
+                    .fold(f32::MAX, f32::min);
+                (pixel, nearest)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match farthest {
+            Some((pixel, distance)) if distance > 0.0 => centroids.push(*pixel),
+            _ => break,
+        }
+    }
+    centroids
+}
+
+fn nearest_centroid(centroids: &[[f32; 3]], pixel: &[f32; 3]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(idx, c)| (idx, delta_e(c, pixel)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+fn delta_e(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// sRGB to CIE Lab under a D65 white point.
+fn rgb_to_lab(rgb: [u8; 3]) -> [f32; 3] {
+    let linear = rgb.map(|v| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_to_rgb(lab: [f32; 3]) -> [u8; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let inverse = |t: f32| {
+        if t.powi(3) > 0.008856 {
+            t.powi(3)
+        } else {
+            (t - 16.0 / 116.0) / 7.787
+        }
+    };
+    let (x, y, z) = (inverse(fx) * 0.95047, inverse(fy), inverse(fz) * 1.08883);
+    let linear = [
+        3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+        0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ];
+    linear.map(|v| {
+        let v = if v <= 0.0031308 {
+            12.92 * v
+        } else {
+            1.055 * v.max(0.0).powf(1.0 / 2.4) - 0.055
+        };
+        (v.clamp(0.0, 1.0) * 255.0).round() as u8
+    })
+}
@@ -0,0 +1,19 @@
+use crate::ditto_repo::{ActivityEntry, DittoRepository};
+use tauri::State;
+
+const DEFAULT_ACTIVITY_PAGE_SIZE: usize = 50;
+
+#[tauri::command]
+pub async fn get_activity(
+    repo: State<'_, DittoRepository>,
+    target: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Vec<ActivityEntry>, String> {
+    repo.get_activity(
+        target.as_deref(),
+        offset.unwrap_or_default(),
+        limit.unwrap_or(DEFAULT_ACTIVITY_PAGE_SIZE),
+    )
+    .await
+}
@@ -0,0 +1,85 @@
+use crate::ditto_repo::{Album, AlbumVisibility, DittoRepository};
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAlbumArgs {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    /// An empty string clears the chosen cover.
+    #[serde(default, alias = "cover_photo_id")]
+    cover_photo_id: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_albums(repo: State<'_, DittoRepository>) -> Result<Vec<Album>, String> {
+    repo.get_albums().await
+}
+
+#[tauri::command]
+pub async fn create_album(
+    repo: State<'_, DittoRepository>,
+    name: String,
+    description: Option<String>,
+    visibility: Option<AlbumVisibility>,
+) -> Result<Album, String> {
+    repo.create_album(
+        &name,
+        description.as_deref().unwrap_or_default(),
+        visibility.unwrap_or(AlbumVisibility::Public),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn update_album(
+    repo: State<'_, DittoRepository>,
+    args: UpdateAlbumArgs,
+) -> Result<Album, String> {
+    repo.update_album(&args.id, args.name, args.description, args.cover_photo_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn set_album_visibility(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    visibility: AlbumVisibility,
+) -> Result<Album, String> {
+    repo.set_album_visibility(&id, visibility).await
+}
+
+#[tauri::command]
+pub async fn delete_album(repo: State<'_, DittoRepository>, id: String) -> Result<(), String> {
+    repo.delete_album(&id).await
+}
+
+#[tauri::command]
+pub async fn add_photos_to_album(
+    repo: State<'_, DittoRepository>,
+    album_id: String,
+    photo_ids: Vec<String>,
+) -> Result<Album, String> {
+    repo.add_photos_to_album(&album_id, photo_ids).await
+}
+
+#[tauri::command]
+pub async fn remove_photos_from_album(
+    repo: State<'_, DittoRepository>,
+    album_id: String,
+    photo_ids: Vec<String>,
+) -> Result<Album, String> {
+    repo.remove_photos_from_album(&album_id, photo_ids).await
+}
+
+#[tauri::command]
+pub async fn reorder_album(
+    repo: State<'_, DittoRepository>,
+    album_id: String,
+    photo_ids: Vec<String>,
+) -> Result<Album, String> {
+    repo.reorder_album(&album_id, photo_ids).await
+}
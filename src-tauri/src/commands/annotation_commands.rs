@@ -0,0 +1,54 @@
+use crate::ditto_repo::{Annotation, AnnotationShape, AnnotationStatus, DittoRepository};
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddAnnotationArgs {
+    #[serde(alias = "photo_id")]
+    photo_id: String,
+    shape: AnnotationShape,
+    text: String,
+}
+
+#[tauri::command]
+pub async fn get_annotations(
+    repo: State<'_, DittoRepository>,
+    photo_id: String,
+) -> Result<Vec<Annotation>, String> {
+    repo.get_annotations(&photo_id).await
+}
+
+/// Without a photo ID this is the retoucher's to-do list for the whole library.
+#[tauri::command]
+pub async fn get_unresolved_annotations(
+    repo: State<'_, DittoRepository>,
+    photo_id: Option<String>,
+) -> Result<Vec<Annotation>, String> {
+    repo.get_unresolved_annotations(photo_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn add_annotation(
+    repo: State<'_, DittoRepository>,
+    args: AddAnnotationArgs,
+) -> Result<Annotation, String> {
+    repo.add_annotation(&args.photo_id, args.shape, &args.text)
+        .await
+}
+
+#[tauri::command]
+pub async fn set_annotation_status(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    status: AnnotationStatus,
+) -> Result<(), String> {
+    repo.set_annotation_status(&id, status).await
+}
+
+#[tauri::command]
+pub async fn delete_annotation(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<(), String> {
+    repo.delete_annotation(&id).await
+}
@@ -0,0 +1,46 @@
+use crate::ditto_repo::{Comment, DittoRepository};
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCommentArgs {
+    #[serde(alias = "photo_id")]
+    photo_id: String,
+    text: String,
+    #[serde(default, alias = "reply_to")]
+    reply_to: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_comments(
+    repo: State<'_, DittoRepository>,
+    photo_id: String,
+) -> Result<Vec<Comment>, String> {
+    repo.get_comments(&photo_id).await
+}
+
+#[tauri::command]
+pub async fn add_comment(
+    repo: State<'_, DittoRepository>,
+    args: AddCommentArgs,
+) -> Result<Comment, String> {
+    repo.add_comment(&args.photo_id, &args.text, args.reply_to)
+        .await
+}
+
+#[tauri::command]
+pub async fn edit_comment(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    text: String,
+) -> Result<Comment, String> {
+    repo.edit_comment(&id, &text).await
+}
+
+#[tauri::command]
+pub async fn delete_comment(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<(), String> {
+    repo.delete_comment(&id).await
+}
@@ -0,0 +1,71 @@
+use crate::ditto_repo::{CullProgress, CullResults, CullSession, DittoRepository};
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordCullChoiceArgs {
+    #[serde(alias = "session_id")]
+    session_id: String,
+    round: u32,
+    #[serde(alias = "left_id")]
+    left_id: String,
+    #[serde(alias = "right_id")]
+    right_id: String,
+    #[serde(alias = "winner_id")]
+    winner_id: String,
+}
+
+#[tauri::command]
+pub async fn get_cull_sessions(
+    repo: State<'_, DittoRepository>,
+) -> Result<Vec<CullSession>, String> {
+    repo.get_cull_sessions().await
+}
+
+#[tauri::command]
+pub async fn create_cull_session(
+    repo: State<'_, DittoRepository>,
+    name: String,
+    photo_ids: Vec<String>,
+) -> Result<CullSession, String> {
+    repo.create_cull_session(&name, photo_ids).await
+}
+
+#[tauri::command]
+pub async fn delete_cull_session(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<(), String> {
+    repo.delete_cull_session(&id).await
+}
+
+#[tauri::command]
+pub async fn get_cull_progress(
+    repo: State<'_, DittoRepository>,
+    session_id: String,
+) -> Result<CullProgress, String> {
+    repo.get_cull_progress(&session_id).await
+}
+
+#[tauri::command]
+pub async fn record_cull_choice(
+    repo: State<'_, DittoRepository>,
+    args: RecordCullChoiceArgs,
+) -> Result<CullProgress, String> {
+    repo.record_cull_choice(
+        &args.session_id,
+        args.round,
+        &args.left_id,
+        &args.right_id,
+        &args.winner_id,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_cull_results(
+    repo: State<'_, DittoRepository>,
+    session_id: String,
+) -> Result<CullResults, String> {
+    repo.get_cull_results(&session_id).await
+}
@@ -0,0 +1,216 @@
+use crate::ditto_repo::{DittoRepository, FaceCluster, FaceRegion, Person, PhotoPayload};
+use crate::face_indexer;
+use crate::faces::{analyze_faces, best_match, DetectedFace, FaceBox, DEFAULT_MATCH_THRESHOLD};
+use serde::Serialize;
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameFaceClusterArgs {
+    #[serde(alias = "cluster_id")]
+    cluster_id: String,
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmFaceRegionsArgs {
+    #[serde(alias = "region_ids")]
+    region_ids: Vec<String>,
+    #[serde(alias = "person_id")]
+    person_id: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergePeopleArgs {
+    #[serde(alias = "source_ids")]
+    source_ids: Vec<String>,
+    #[serde(alias = "target_id")]
+    target_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaceMatch {
+    pub path: String,
+    pub score: f32,
+    pub bounding_box: FaceBox,
+    /// Which of the target image's faces this candidate face matched.
+    pub target_face_index: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaceRecognitionResult {
+    pub target_name: String,
+    pub matched_paths: Vec<String>,
+    pub matches: Vec<FaceMatch>,
+}
+
+#[tauri::command]
+pub async fn recognize_faces(
+    repo: State<'_, DittoRepository>,
+    target_image_path: String,
+    target_name: String,
+    candidate_image_paths: Vec<String>,
+    threshold: Option<f32>,
+) -> Result<FaceRecognitionResult, String> {
+    let threshold = threshold.unwrap_or(DEFAULT_MATCH_THRESHOLD);
+    let library = repo.get_photos().await?;
+
+    let target_faces: Vec<Vec<f32>> = faces_for_path(&repo, &library, &target_image_path)
+        .await?
+        .into_iter()
+        .map(|face| face.embedding)
+        .collect();
+
+    let mut matches = Vec::new();
+    if !target_faces.is_empty() {
+        for path in candidate_image_paths {
+            let faces = match faces_for_path(&repo, &library, &path).await {
+                Ok(faces) => faces,
+                Err(error) => {
+                    eprintln!("Face analysis failed for {path}: {error}");
+                    continue;
+                }
+            };
+            for face in faces {
+                let Some((target_face_index, score)) = best_match(&face.embedding, &target_faces)
+                else {
+                    continue;
+                };
+                if score >= threshold {
+                    matches.push(FaceMatch {
+                        path: path.clone(),
+                        score,
+                        bounding_box: face.bounding_box,
+                        target_face_index,
+                    });
+                }
+            }
+        }
+    }
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut matched_paths: Vec<String> = Vec::new();
+    for m in &matches {
+        if !matched_paths.contains(&m.path) {
+            matched_paths.push(m.path.clone());
+        }
+    }
+
+    Ok(FaceRecognitionResult {
+        target_name,
+        matched_paths,
+        matches,
+    })
+}
+
+/// Library photos are answered from the local cache or the synced face regions;
+/// only unknown images are analyzed on the spot.
+async fn faces_for_path(
+    repo: &DittoRepository,
+    library: &[PhotoPayload],
+    path: &str,
+) -> Result<Vec<DetectedFace>, String> {
+    let Some(photo) = library.iter().find(|photo| photo.image_path == path) else {
+        let path = path.to_string();
+        return tauri::async_runtime::spawn_blocking(move || analyze_faces(&path))
+            .await
+            .map_err(|e| format!("Face analysis task failed: {e}"))?;
+    };
+    if let Some(faces) = face_indexer::cached_faces(photo) {
+        return Ok(faces);
+    }
+    let synced = repo.get_detected_faces(&photo.id).await?;
+    if !synced.is_empty() || !std::path::Path::new(&photo.image_path).exists() {
+        return Ok(synced);
+    }
+    face_indexer::analyze_photo(photo).await
+}
+
+#[tauri::command]
+pub async fn index_photo_faces(
+    repo: State<'_, DittoRepository>,
+    photo_id: String,
+    threshold: Option<f32>,
+) -> Result<Vec<FaceRegion>, String> {
+    let photo = repo
+        .get_photo(&photo_id)
+        .await?
+        .ok_or_else(|| format!("Photo {photo_id} not found"))?;
+    let faces = face_indexer::analyze_photo(&photo).await?;
+    repo.store_photo_faces(&photo_id, faces).await?;
+    repo.suggest_people(threshold.unwrap_or(DEFAULT_MATCH_THRESHOLD))
+        .await?;
+    repo.get_face_regions(Some(&photo_id)).await
+}
+
+#[tauri::command]
+pub async fn get_face_regions(
+    repo: State<'_, DittoRepository>,
+    photo_id: Option<String>,
+) -> Result<Vec<FaceRegion>, String> {
+    repo.get_face_regions(photo_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn get_people(repo: State<'_, DittoRepository>) -> Result<Vec<Person>, String> {
+    repo.get_people().await
+}
+
+#[tauri::command]
+pub async fn cluster_faces(
+    repo: State<'_, DittoRepository>,
+    threshold: Option<f32>,
+) -> Result<Vec<FaceCluster>, String> {
+    repo.cluster_faces(threshold.unwrap_or(DEFAULT_MATCH_THRESHOLD))
+        .await
+}
+
+#[tauri::command]
+pub async fn get_face_clusters(
+    repo: State<'_, DittoRepository>,
+) -> Result<Vec<FaceCluster>, String> {
+    repo.get_face_clusters().await
+}
+
+#[tauri::command]
+pub async fn name_face_cluster(
+    repo: State<'_, DittoRepository>,
+    args: NameFaceClusterArgs,
+) -> Result<Person, String> {
+    repo.name_face_cluster(&args.cluster_id, &args.name).await
+}
+
+#[tauri::command]
+pub async fn confirm_face_regions(
+    repo: State<'_, DittoRepository>,
+    args: ConfirmFaceRegionsArgs,
+) -> Result<(), String> {
+    repo.confirm_face_regions(args.region_ids, &args.person_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn merge_people(
+    repo: State<'_, DittoRepository>,
+    args: MergePeopleArgs,
+) -> Result<(), String> {
+    repo.merge_people(args.source_ids, &args.target_id).await
+}
+
+#[tauri::command]
+pub async fn split_face_regions(
+    repo: State<'_, DittoRepository>,
+    region_ids: Vec<String>,
+) -> Result<String, String> {
+    repo.split_face_regions(region_ids).await
+}
+
+#[tauri::command]
+pub async fn reject_face_regions(
+    repo: State<'_, DittoRepository>,
+    region_ids: Vec<String>,
+) -> Result<(), String> {
+    repo.reject_face_regions(region_ids).await
+}
@@ -0,0 +1,100 @@
+use crate::ditto_repo::{DittoRepository, PhotoPayload};
+use crate::metadata::{write_metadata_to_original, MetadataWriteTarget};
+use serde::Serialize;
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCaptureTimeArgs {
+    #[serde(alias = "photo_ids")]
+    photo_ids: Vec<String>,
+    #[serde(alias = "reference_id")]
+    reference_id: String,
+    /// The correct capture time of the reference photo.
+    #[serde(alias = "reference_datetime")]
+    reference_datetime: String,
+    #[serde(default, alias = "write_target")]
+    write_target: Option<MetadataWriteTarget>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MetadataEditReport {
+    pub updated: Vec<String>,
+    pub written: Vec<String>,
+    /// Not written to the original, or not changed at all (e.g. no capture time to shift).
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn shift_photos_capture_time(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    offset_seconds: i64,
+    write_target: Option<MetadataWriteTarget>,
+) -> Result<MetadataEditReport, String> {
+    let (updated, skipped) = repo.shift_photos_capture_time(ids, offset_seconds).await?;
+    let mut report = build_report(&repo, updated, write_target);
+    report.skipped.extend(skipped);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn sync_photos_capture_time(
+    repo: State<'_, DittoRepository>,
+    args: SyncCaptureTimeArgs,
+) -> Result<MetadataEditReport, String> {
+    let (updated, skipped) = repo
+        .sync_photos_capture_time(args.photo_ids, &args.reference_id, &args.reference_datetime)
+        .await?;
+    let mut report = build_report(&repo, updated, args.write_target);
+    report.skipped.extend(skipped);
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn set_photos_location(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    latitude: f64,
+    longitude: f64,
+    write_target: Option<MetadataWriteTarget>,
+) -> Result<MetadataEditReport, String> {
+    let updated = repo.update_photos_location(ids, latitude, longitude).await?;
+    Ok(build_report(&repo, updated, write_target))
+}
+
+/// Only the authoring peer has the original on disk, so everyone else just syncs the change.
+/// Virtual copies share their source's original, so their edits are never written to it.
+pub(crate) fn build_report(
+    repo: &DittoRepository,
+    updated: Vec<PhotoPayload>,
+    write_target: Option<MetadataWriteTarget>,
+) -> MetadataEditReport {
+    let mut report = MetadataEditReport::default();
+    let local_peer_key = repo.local_peer_key();
+
+    for photo in updated {
+        report.updated.push(photo.id.clone());
+        let Some(target) = write_target else {
+            continue;
+        };
+        let metadata = photo.metadata.clone().unwrap_or_default();
+        let is_local_original = photo.author_peer_id.as_deref() == Some(local_peer_key.as_str())
+            && photo.source_photo_id.is_none()
+            && std::path::Path::new(&photo.image_path).exists();
+        if !is_local_original {
+            report.skipped.push(photo.id);
+            continue;
+        }
+        match write_metadata_to_original(&photo.image_path, &metadata, &photo.tags, target) {
+            Ok(()) => report.written.push(photo.id),
+            Err(error) => {
+                eprintln!("{error}");
+                report.errors.push(error);
+            }
+        }
+    }
+
+    report
+}
@@ -1 +1,15 @@
-pub mod photo_library_commands;
\ No newline at end of file
+pub mod activity_commands;
+pub mod album_commands;
+pub mod annotation_commands;
+pub mod comment_commands;
+pub mod cull_commands;
+pub mod face_commands;
+pub mod metadata_commands;
+pub mod photo_library_commands;
+pub mod quality_commands;
+pub mod reject_commands;
+pub mod search_commands;
+pub mod similarity_commands;
+pub mod smart_album_commands;
+pub mod tag_commands;
+pub mod vote_commands;
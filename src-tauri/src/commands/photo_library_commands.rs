@@ -1,8 +1,10 @@
-use crate::ditto_repo::{DittoRepository, Photo, PhotoPayload};
+use crate::ditto_repo::{
+    ColorLabel, ConfigHistory, DittoRepository, ImageMetadata, Photo, PhotoPayload,
+};
+use crate::metadata::read_image_metadata;
+use crate::perceptual_hash::PerceptualHash;
 use base64::{engine::general_purpose, Engine as _};
 use image::{DynamicImage, ImageFormat};
-use rexif::{ExifTag, TagValue};
-use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::io::Cursor;
 use tauri::{AppHandle, State};
@@ -38,15 +40,6 @@ pub struct ClearPhotoStackArgs {
 
 const UPSERT_BATCH_SIZE: usize = 25;
 
-#[derive(Debug, Serialize)]
-pub struct ImageMetadata {
-    pub datetime: Option<String>,
-    pub latitude: Option<f64>,
-    pub longitude: Option<f64>,
-    pub make: Option<String>,
-    pub model: Option<String>,
-}
-
 pub fn generate_image_id(img: &DynamicImage) -> String {
     let thumb = img.resize_exact(300, 300, image::imageops::FilterType::Lanczos3);
     let mut hasher = Sha256::new();
@@ -56,141 +49,7 @@ pub fn generate_image_id(img: &DynamicImage) -> String {
 
 #[tauri::command]
 pub async fn analyze_image_metadata(path: String) -> Result<ImageMetadata, String> {
-    let data = match rexif::parse_file(&path) {
-        Ok(d) => d,
-        Err(_) => {
-            return Ok(ImageMetadata {
-                datetime: None,
-                latitude: None,
-                longitude: None,
-                make: None,
-                model: None,
-            })
-        }
-    };
-
-    let mut out = ImageMetadata {
-        datetime: None,
-        latitude: None,
-        longitude: None,
-        make: None,
-        model: None,
-    };
-
-    for entry in &data.entries {
-        match entry.tag {
-            ExifTag::DateTimeOriginal | ExifTag::DateTime => {
-                if out.datetime.is_none() {
-                    out.datetime = Some(entry.value_more_readable.to_string());
-                }
-            }
-            ExifTag::Make => out.make = Some(entry.value_more_readable.to_string()),
-            ExifTag::Model => out.model = Some(entry.value_more_readable.to_string()),
-            ExifTag::GPSLatitude => out.latitude = parse_gps_to_decimal(&entry.value),
-            ExifTag::GPSLongitude => out.longitude = parse_gps_to_decimal(&entry.value),
-            ExifTag::GPSLatitudeRef => {
-                if entry.value_more_readable.contains('S') {
-                    out.latitude = out.latitude.map(|lat| -lat.abs());
-                }
-            }
-            ExifTag::GPSLongitudeRef => {
-                if entry.value_more_readable.contains('W') {
-                    out.longitude = out.longitude.map(|lon| -lon.abs());
-                }
-            }
-            _ => {}
-        }
-    }
-
-    Ok(out)
-}
-
-fn parse_gps_to_decimal(value: &TagValue) -> Option<f64> {
-    if let TagValue::URational(values) = value {
-        if values.len() >= 3 {
-            let deg = values[0].value();
-            let min = values[1].value();
-            let sec = values[2].value();
-            return Some(deg + (min / 60.0) + (sec / 3600.0));
-        }
-    }
-    None
-}
-
-#[derive(Debug, Serialize)]
-pub struct FaceRecognitionResult {
-    pub matched_paths: Vec<String>,
-}
-
-#[tauri::command]
-pub async fn recognize_faces(
-    target_image_path: String,
-    _target_name: String,
-    candidate_image_paths: Vec<String>,
-) -> Result<FaceRecognitionResult, String> {
-    #[cfg(target_os = "macos")]
-    {
-        if !contains_face(&target_image_path)? {
-            return Ok(FaceRecognitionResult {
-                matched_paths: vec![],
-            });
-        }
-        let mut matched = Vec::new();
-        for p in candidate_image_paths {
-            if contains_face(&p)? {
-                matched.push(p);
-            }
-        }
-        return Ok(FaceRecognitionResult {
-            matched_paths: matched,
-        });
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        Ok(FaceRecognitionResult {
-            matched_paths: vec![],
-        })
-    }
-}
-
-#[cfg(all(target_os = "macos", feature = "vision_face_detect"))]
-fn contains_face(path: &str) -> Result<bool, String> {
-    use objc2::rc::Retained;
-    use objc2_foundation::{NSData, NSDataReadingOptions, NSDictionary, NSString, NSURL};
-    use objc2_vision::{VNDetectFaceRectanglesRequest, VNImageRequestHandler, VNRequest};
-
-    unsafe {
-        let nsurl = NSURL::fileURLWithPath_isDirectory(&NSString::from_str(path), false);
-        let data = NSData::initWithContentsOfURL_options_error(
-            NSData::alloc(),
-            &nsurl,
-            NSDataReadingOptions(0),
-        )
-        .map_err(|_| "Failed to read image data".to_string())?;
-
-        let opts = NSDictionary::new();
-        let handler = VNImageRequestHandler::initWithData_options(
-            VNImageRequestHandler::alloc(),
-            &data,
-            &opts,
-        );
-
-        let req = VNDetectFaceRectanglesRequest::new();
-        let mut reqs: [Retained<VNRequest>; 1] = [Retained::cast(req.retain())];
-
-        let _ = handler
-            .performRequests_error(&mut reqs, std::ptr::null_mut())
-            .map_err(|_| "Vision face detection failed".to_string())?;
-
-        let results = req.results();
-        Ok(results.is_some() && results.unwrap().count() > 0)
-    }
-}
-
-#[cfg(any(not(target_os = "macos"), not(feature = "vision_face_detect")))]
-fn contains_face(_path: &str) -> Result<bool, String> {
-    Ok(false)
+    Ok(read_image_metadata(&path))
 }
 
 fn image_to_base64(img: &DynamicImage, format: ImageFormat) -> String {
@@ -228,6 +87,9 @@ fn process_image_file(path: String) -> Result<Photo, String> {
     let id = generate_image_id(&img);
     let thumbnail = img.thumbnail(300, 300);
     let base64_content = image_to_base64(&thumbnail, ImageFormat::Jpeg);
+    let metadata = read_image_metadata(&path);
+    let perceptual_hash = PerceptualHash::compute(&thumbnail);
+    let palette = crate::color_palette::extract_palette(&thumbnail);
     Ok(Photo {
         id,
         filename: std::path::Path::new(&path)
@@ -241,6 +103,11 @@ fn process_image_file(path: String) -> Result<Photo, String> {
         favorite: false,
         stack_id: None,
         is_stack_primary: false,
+        metadata: Some(metadata),
+        perceptual_hash: Some(perceptual_hash),
+        // Scored in the background by the quality indexer.
+        quality: None,
+        palette,
     })
 }
 
@@ -390,6 +257,16 @@ pub async fn add_photos_to_library(
     }
 }
 
+/// Adds another library item sharing the photo's original, with its own edits and ratings.
+#[tauri::command]
+pub async fn create_virtual_copy(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    name: Option<String>,
+) -> Result<PhotoPayload, String> {
+    repo.create_virtual_copy(&id, name).await
+}
+
 #[tauri::command]
 pub async fn clear_library(repo: State<'_, DittoRepository>) -> Result<(), String> {
     repo.clear_library().await
@@ -411,6 +288,39 @@ pub async fn save_photo_config(
     repo.update_photo_config(&id, config).await
 }
 
+#[tauri::command]
+pub async fn get_config_history(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<ConfigHistory, String> {
+    repo.get_config_history(&id).await
+}
+
+#[tauri::command]
+pub async fn undo_photo_config(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<ConfigHistory, String> {
+    repo.undo_photo_config(&id).await
+}
+
+#[tauri::command]
+pub async fn redo_photo_config(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<ConfigHistory, String> {
+    repo.redo_photo_config(&id).await
+}
+
+#[tauri::command]
+pub async fn revert_photo_config(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    version_id: String,
+) -> Result<ConfigHistory, String> {
+    repo.revert_photo_config(&id, &version_id).await
+}
+
 #[tauri::command]
 pub async fn set_photo_favorite(
     repo: State<'_, DittoRepository>,
@@ -429,6 +339,42 @@ pub async fn set_photos_favorite(
     repo.update_photos_favorite(ids, favorite).await
 }
 
+#[tauri::command]
+pub async fn set_photo_rating(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    rating: u8,
+) -> Result<(), String> {
+    repo.update_photo_rating(&id, rating).await
+}
+
+#[tauri::command]
+pub async fn set_photos_rating(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    rating: u8,
+) -> Result<(), String> {
+    repo.update_photos_rating(ids, rating).await
+}
+
+#[tauri::command]
+pub async fn set_photo_color_labels(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    color_labels: Vec<ColorLabel>,
+) -> Result<(), String> {
+    repo.update_photo_color_labels(&id, color_labels).await
+}
+
+#[tauri::command]
+pub async fn set_photos_color_labels(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    color_labels: Vec<ColorLabel>,
+) -> Result<(), String> {
+    repo.update_photos_color_labels(ids, color_labels).await
+}
+
 #[tauri::command]
 pub async fn set_photo_stack(
     repo: State<'_, DittoRepository>,
@@ -0,0 +1,21 @@
+use crate::ditto_repo::{DittoRepository, RankedPhoto};
+use tauri::State;
+
+/// Recomputes quality scores from the originals on this device, e.g. for photos imported before scoring existed.
+#[tauri::command]
+pub async fn score_photos(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+) -> Result<Vec<String>, String> {
+    repo.score_photos(ids).await
+}
+
+#[tauri::command]
+pub async fn rank_stack(
+    repo: State<'_, DittoRepository>,
+    stack_id: String,
+    apply_primary: Option<bool>,
+) -> Result<Vec<RankedPhoto>, String> {
+    repo.rank_stack(&stack_id, apply_primary.unwrap_or(false))
+        .await
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::ditto_repo::DittoRepository;
+use crate::rejects::{dispose_original, PurgeDestination};
+use serde::Serialize;
+use tauri::State;
+
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeReport {
+    /// Photos removed from the library.
+    pub removed: Vec<String>,
+    /// Originals moved to the trash or the rejects folder.
+    pub moved: Vec<String>,
+    /// Photos left alone because their original belongs to another peer or is missing, or because
+    /// virtual copies that aren't rejected still use it.
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[tauri::command]
+pub async fn set_photo_rejected(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    rejected: bool,
+) -> Result<(), String> {
+    repo.update_photos_rejected(vec![id], rejected).await
+}
+
+#[tauri::command]
+pub async fn set_photos_rejected(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    rejected: bool,
+) -> Result<(), String> {
+    repo.update_photos_rejected(ids, rejected).await
+}
+
+/// Removes every rejected photo from the library. When the originals should go too, only photos
+/// authored by this peer are purged; the rest stay in the library so their author can purge them.
+/// A source photo is only purged once none of its virtual copies are left.
+#[tauri::command]
+pub async fn purge_rejected(
+    repo: State<'_, DittoRepository>,
+    destination: Option<PurgeDestination>,
+) -> Result<PurgeReport, String> {
+    let destination = destination.unwrap_or(PurgeDestination::LibraryOnly);
+    let local_peer_key = repo.local_peer_key();
+    let mut report = PurgeReport::default();
+
+    let photos = repo.get_photos().await?;
+    let mut remaining_copies: HashMap<String, usize> = HashMap::new();
+    for source_id in photos.iter().filter_map(|photo| photo.source_photo_id.clone()) {
+        *remaining_copies.entry(source_id).or_default() += 1;
+    }
+    let mut rejected: Vec<_> = photos.into_iter().filter(|photo| photo.rejected).collect();
+    // Copies first, so a source whose copies are all rejected can go in the same purge.
+    rejected.sort_by_key(|photo| photo.source_photo_id.is_none());
+
+    for photo in rejected {
+        if remaining_copies.get(&photo.id).is_some_and(|count| *count > 0) {
+            report.skipped.push(photo.id);
+            continue;
+        }
+        // A virtual copy shares its source's original, which stays unless the source is purged.
+        let dispose = destination != PurgeDestination::LibraryOnly && photo.source_photo_id.is_none();
+        if dispose {
+            let is_local_original = photo.author_peer_id.as_deref() == Some(local_peer_key.as_str())
+                && std::path::Path::new(&photo.image_path).exists();
+            if !is_local_original {
+                report.skipped.push(photo.id);
+                continue;
+            }
+        }
+        // The library entry goes first: if removing it fails, its original must still be there.
+        if let Err(error) = repo.remove_photo(&photo.id).await {
+            eprintln!("{error}");
+            report.errors.push(error);
+            continue;
+        }
+        if let Some(count) = photo
+            .source_photo_id
+            .as_ref()
+            .and_then(|source_id| remaining_copies.get_mut(source_id))
+        {
+            *count -= 1;
+        }
+        report.removed.push(photo.id.clone());
+        if dispose {
+            match dispose_original(&photo.image_path, &destination) {
+                Ok(_) => report.moved.push(photo.id),
+                Err(error) => {
+                    eprintln!("{error}");
+                    report.errors.push(error);
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,90 @@
+use crate::ditto_repo::{DittoRepository, TagCount};
+use crate::semantic_search::SemanticMatch;
+use crate::semantic_indexer::cached_embedding;
+use tauri::State;
+
+const DEFAULT_SEMANTIC_LIMIT: usize = 50;
+
+/// Photos still waiting for the background indexer are left out until their embedding exists.
+#[tauri::command]
+pub async fn semantic_search(
+    repo: State<'_, DittoRepository>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SemanticMatch>, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query_embedding =
+        tauri::async_runtime::spawn_blocking(move || crate::semantic_search::embed_text(&query))
+            .await
+            .map_err(|e| format!("Semantic search task failed: {e}"))??;
+    let limit = limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT);
+    if let Some(nearest) = crate::similarity_index::nearest_by_clip(&query_embedding, limit) {
+        return Ok(nearest
+            .into_iter()
+            .map(|(id, score)| SemanticMatch { id, score })
+            .collect());
+    }
+    let embeddings: Vec<(String, Vec<f32>)> = repo
+        .get_photos()
+        .await?
+        .iter()
+        .filter_map(|photo| Some((photo.id.clone(), cached_embedding(photo)?)))
+        .collect();
+    Ok(crate::semantic_search::rank(&query_embedding, &embeddings, limit))
+}
+
+/// Photos with similar content to `id`, by CLIP embedding rather than pixel layout.
+#[tauri::command]
+pub async fn find_related_photos(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<SemanticMatch>, String> {
+    let limit = limit.unwrap_or(DEFAULT_SEMANTIC_LIMIT);
+    let photos = repo.get_photos().await?;
+    let embedding = photos
+        .iter()
+        .find(|photo| photo.id == id)
+        .and_then(cached_embedding)
+        .ok_or_else(|| format!("Photo {id} has not been indexed for semantic search yet"))?;
+    let matches = match crate::similarity_index::nearest_by_clip(&embedding, limit + 1) {
+        Some(nearest) => nearest
+            .into_iter()
+            .map(|(id, score)| SemanticMatch { id, score })
+            .collect(),
+        None => {
+            let embeddings: Vec<(String, Vec<f32>)> = photos
+                .iter()
+                .filter_map(|photo| Some((photo.id.clone(), cached_embedding(photo)?)))
+                .collect();
+            crate::semantic_search::rank(&embedding, &embeddings, limit + 1)
+        }
+    };
+    Ok(matches
+        .into_iter()
+        .filter(|candidate| candidate.id != id)
+        .take(limit)
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_machine_tags(
+    repo: State<'_, DittoRepository>,
+    min_confidence: Option<f32>,
+) -> Result<Vec<TagCount>, String> {
+    repo.get_machine_tag_counts(min_confidence.unwrap_or(0.0))
+        .await
+}
+
+#[tauri::command]
+pub async fn search_machine_tags(
+    repo: State<'_, DittoRepository>,
+    query: String,
+    min_confidence: Option<f32>,
+) -> Result<Vec<String>, String> {
+    repo.search_machine_tags(&query, min_confidence.unwrap_or(0.0))
+        .await
+}
@@ -0,0 +1,64 @@
+use crate::ditto_repo::{ColorMatch, DittoRepository, SimilarPhoto};
+use crate::stack_suggestions::StackSuggestion;
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptStackSuggestionArgs {
+    #[serde(alias = "photo_ids")]
+    photo_ids: Vec<String>,
+    #[serde(alias = "primary_id")]
+    primary_id: String,
+}
+
+/// Up to this many differing pHash bits still reads as "the same shot" after re-encoding or resizing.
+const DEFAULT_SIMILARITY_THRESHOLD: u32 = 10;
+/// Loose enough to find "warm orange" shots from a single swatch.
+const DEFAULT_COLOR_DISTANCE: f32 = 20.0;
+
+#[tauri::command]
+pub async fn find_similar_photos(
+    repo: State<'_, DittoRepository>,
+    id: String,
+    threshold: Option<u32>,
+) -> Result<Vec<SimilarPhoto>, String> {
+    repo.find_similar_photos(&id, threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD))
+        .await
+}
+
+#[tauri::command]
+pub async fn get_stack_suggestions(
+    repo: State<'_, DittoRepository>,
+    threshold: Option<u32>,
+) -> Result<Vec<StackSuggestion>, String> {
+    repo.get_stack_suggestions(threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD))
+        .await
+}
+
+/// Returns the ID of the newly created stack.
+#[tauri::command]
+pub async fn accept_stack_suggestion(
+    repo: State<'_, DittoRepository>,
+    args: AcceptStackSuggestionArgs,
+) -> Result<String, String> {
+    repo.accept_stack_suggestion(args.photo_ids, args.primary_id)
+        .await
+}
+
+#[tauri::command]
+pub async fn dismiss_stack_suggestion(
+    repo: State<'_, DittoRepository>,
+    photo_ids: Vec<String>,
+) -> Result<(), String> {
+    repo.dismiss_stack_suggestion(photo_ids).await
+}
+
+#[tauri::command]
+pub async fn search_photos_by_color(
+    repo: State<'_, DittoRepository>,
+    color: String,
+    max_distance: Option<f32>,
+) -> Result<Vec<ColorMatch>, String> {
+    repo.search_photos_by_color(&color, max_distance.unwrap_or(DEFAULT_COLOR_DISTANCE))
+        .await
+}
@@ -0,0 +1,52 @@
+use crate::ditto_repo::{DittoRepository, SmartAlbum};
+use crate::smart_albums::SmartAlbumRules;
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveSmartAlbumArgs {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    rules: SmartAlbumRules,
+}
+
+#[tauri::command]
+pub async fn get_smart_albums(
+    repo: State<'_, DittoRepository>,
+) -> Result<Vec<SmartAlbum>, String> {
+    repo.get_smart_albums().await
+}
+
+#[tauri::command]
+pub async fn save_smart_album(
+    repo: State<'_, DittoRepository>,
+    args: SaveSmartAlbumArgs,
+) -> Result<SmartAlbum, String> {
+    repo.save_smart_album(args.id, args.name, args.rules).await
+}
+
+#[tauri::command]
+pub async fn delete_smart_album(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<(), String> {
+    repo.delete_smart_album(&id).await
+}
+
+#[tauri::command]
+pub async fn get_smart_album_photo_ids(
+    repo: State<'_, DittoRepository>,
+    id: String,
+) -> Result<Vec<String>, String> {
+    repo.get_smart_album_photo_ids(&id).await
+}
+
+#[tauri::command]
+pub async fn query_photo_ids(
+    repo: State<'_, DittoRepository>,
+    rules: SmartAlbumRules,
+) -> Result<Vec<String>, String> {
+    repo.query_photo_ids(&rules).await
+}
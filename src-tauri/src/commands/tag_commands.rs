@@ -0,0 +1,60 @@
+use crate::commands::metadata_commands::{build_report, MetadataEditReport};
+use crate::ditto_repo::{DittoRepository, TagCount};
+use crate::metadata::MetadataWriteTarget;
+use tauri::State;
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeTagsArgs {
+    sources: Vec<String>,
+    target: String,
+    #[serde(default, alias = "write_target")]
+    write_target: Option<MetadataWriteTarget>,
+}
+
+#[tauri::command]
+pub async fn add_photos_tags(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    tags: Vec<String>,
+    write_target: Option<MetadataWriteTarget>,
+) -> Result<MetadataEditReport, String> {
+    let updated = repo.add_photos_tags(ids, tags).await?;
+    Ok(build_report(&repo, updated, write_target))
+}
+
+#[tauri::command]
+pub async fn remove_photos_tags(
+    repo: State<'_, DittoRepository>,
+    ids: Vec<String>,
+    tags: Vec<String>,
+    write_target: Option<MetadataWriteTarget>,
+) -> Result<MetadataEditReport, String> {
+    let updated = repo.remove_photos_tags(ids, tags).await?;
+    Ok(build_report(&repo, updated, write_target))
+}
+
+#[tauri::command]
+pub async fn rename_tag(
+    repo: State<'_, DittoRepository>,
+    from: String,
+    to: String,
+    write_target: Option<MetadataWriteTarget>,
+) -> Result<MetadataEditReport, String> {
+    let updated = repo.rename_tag(&from, &to).await?;
+    Ok(build_report(&repo, updated, write_target))
+}
+
+#[tauri::command]
+pub async fn merge_tags(
+    repo: State<'_, DittoRepository>,
+    args: MergeTagsArgs,
+) -> Result<MetadataEditReport, String> {
+    let updated = repo.merge_tags(args.sources, &args.target).await?;
+    Ok(build_report(&repo, updated, args.write_target))
+}
+
+#[tauri::command]
+pub async fn get_tag_counts(repo: State<'_, DittoRepository>) -> Result<Vec<TagCount>, String> {
+    repo.get_tag_counts().await
+}
@@ -0,0 +1,30 @@
+use crate::ditto_repo::DittoRepository;
+use crate::votes::{VoteChoice, VoteFilter, VoteSummary};
+use tauri::State;
+
+#[tauri::command]
+pub async fn cast_votes(
+    repo: State<'_, DittoRepository>,
+    photo_ids: Vec<String>,
+    choice: Option<VoteChoice>,
+) -> Result<(), String> {
+    repo.cast_votes(photo_ids, choice).await
+}
+
+#[tauri::command]
+pub async fn cast_ratings(
+    repo: State<'_, DittoRepository>,
+    photo_ids: Vec<String>,
+    rating: Option<u8>,
+) -> Result<(), String> {
+    repo.cast_ratings(photo_ids, rating).await
+}
+
+#[tauri::command]
+pub async fn get_vote_summaries(
+    repo: State<'_, DittoRepository>,
+    photo_ids: Option<Vec<String>>,
+    filter: Option<VoteFilter>,
+) -> Result<Vec<VoteSummary>, String> {
+    repo.get_vote_summaries(photo_ids, filter).await
+}
@@ -0,0 +1,247 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+/// A single recorded decision: `winner_id` beat the other side of `left_id` vs `right_id`.
+#[derive(Clone, Debug)]
+pub struct CullChoice {
+    pub round: u32,
+    pub left_id: String,
+    pub right_id: String,
+    pub winner_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CullMatch {
+    pub round: u32,
+    pub left_id: String,
+    pub right_id: String,
+}
+
+/// One peer's pass through a session's bracket.
+#[derive(Clone, Debug, Serialize)]
+pub struct Bracket {
+    pub decided: usize,
+    /// A single-elimination bracket over `n` photos always takes `n - 1` matches, byes included.
+    pub total: usize,
+    pub complete: bool,
+    /// Undecided matches of the current round, in bracket order.
+    pub pending: Vec<CullMatch>,
+    /// Best first: photos still in the running, then by the round they were knocked out in.
+    pub ranking: Vec<String>,
+    #[serde(skip)]
+    matches: Vec<CullMatch>,
+    #[serde(skip)]
+    eliminated: HashSet<String>,
+    #[serde(skip)]
+    wins: HashMap<String, u32>,
+}
+
+impl Bracket {
+    /// Whether `left_id` vs `right_id` is a match this peer has reached, decided or not.
+    pub fn contains(&self, round: u32, left_id: &str, right_id: &str) -> bool {
+        self.matches
+            .iter()
+            .any(|m| m.round == round && m.left_id == left_id && m.right_id == right_id)
+    }
+
+    pub fn wins_and_losses(&self, photo_id: &str) -> (u32, u32) {
+        (
+            self.wins.get(photo_id).copied().unwrap_or_default(),
+            u32::from(self.eliminated.contains(photo_id)),
+        )
+    }
+}
+
+/// Replays a bracket from the session's photo order and one peer's choices. Neighbours are
+/// paired each round and an odd photo out gets a bye; a round only advances once all its
+/// matches are decided. Choices for pairs the bracket never reaches (e.g. after an earlier
+/// decision was changed) are ignored.
+pub fn play(photo_ids: &[String], choices: &[CullChoice]) -> Bracket {
+    let decisions: HashMap<(u32, &str, &str), &str> = choices
+        .iter()
+        .filter(|c| c.winner_id == c.left_id || c.winner_id == c.right_id)
+        .map(|c| ((c.round, c.left_id.as_str(), c.right_id.as_str()), c.winner_id.as_str()))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut contestants: Vec<&str> = photo_ids
+        .iter()
+        .map(String::as_str)
+        .filter(|id| seen.insert(*id))
+        .collect();
+    let order: HashMap<&str, usize> = contestants.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let total = contestants.len().saturating_sub(1);
+
+    let mut matches = Vec::new();
+    let mut pending = Vec::new();
+    let mut eliminated_in: HashMap<&str, u32> = HashMap::new();
+    let mut wins: HashMap<String, u32> = HashMap::new();
+    let mut decided = 0;
+    let mut round = 0;
+    while contestants.len() > 1 {
+        let mut advancing = Vec::with_capacity(contestants.len() / 2 + 1);
+        for pair in contestants.chunks(2) {
+            let [left, right] = pair else {
+                advancing.push(pair[0]);
+                continue;
+            };
+            let current = CullMatch {
+                round,
+                left_id: left.to_string(),
+                right_id: right.to_string(),
+            };
+            match decisions.get(&(round, *left, *right)) {
+                Some(&winner) => {
+                    let loser = if winner == *left { *right } else { *left };
+                    eliminated_in.insert(loser, round);
+                    *wins.entry(winner.to_string()).or_default() += 1;
+                    advancing.push(winner);
+                    decided += 1;
+                }
+                None => pending.push(current.clone()),
+            }
+            matches.push(current);
+        }
+        if !pending.is_empty() {
+            break;
+        }
+        contestants = advancing;
+        round += 1;
+    }
+
+    let mut ranking: Vec<&str> = order.keys().copied().collect();
+    ranking.sort_by(|a, b| {
+        let rank = |id: &str| eliminated_in.get(id).copied().unwrap_or(u32::MAX);
+        rank(b).cmp(&rank(a)).then(order[a].cmp(&order[b]))
+    });
+
+    Bracket {
+        decided,
+        total,
+        complete: pending.is_empty(),
+        pending,
+        ranking: ranking.into_iter().map(str::to_string).collect(),
+        matches,
+        eliminated: eliminated_in.keys().map(|id| id.to_string()).collect(),
+        wins,
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CullStanding {
+    pub photo_id: String,
+    /// Mean normalized placement across peers that have made at least one choice, `0.0..=1.0`.
+    pub score: f32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+/// Combines each participating peer's ranking into one, best first.
+pub fn combine(photo_ids: &[String], brackets: &[&Bracket]) -> Vec<CullStanding> {
+    let mut standings: Vec<CullStanding> = photo_ids
+        .iter()
+        .map(|photo_id| {
+            let mut standing = CullStanding {
+                photo_id: photo_id.clone(),
+                score: 0.0,
+                wins: 0,
+                losses: 0,
+            };
+            for bracket in brackets {
+                let last = bracket.ranking.len().saturating_sub(1).max(1) as f32;
+                if let Some(position) = bracket.ranking.iter().position(|id| id == photo_id) {
+                    standing.score += (last - position as f32).max(0.0) / last;
+                }
+                let (wins, losses) = bracket.wins_and_losses(photo_id);
+                standing.wins += wins;
+                standing.losses += losses;
+            }
+            if !brackets.is_empty() {
+                standing.score /= brackets.len() as f32;
+            }
+            standing
+        })
+        .collect();
+    standings.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.wins.cmp(&a.wins))
+    });
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    fn choice(round: u32, left_id: &str, right_id: &str, winner_id: &str) -> CullChoice {
+        CullChoice {
+            round,
+            left_id: left_id.to_string(),
+            right_id: right_id.to_string(),
+            winner_id: winner_id.to_string(),
+        }
+    }
+
+    fn cull_match(round: u32, left_id: &str, right_id: &str) -> CullMatch {
+        CullMatch {
+            round,
+            left_id: left_id.to_string(),
+            right_id: right_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn the_odd_photo_out_gets_a_bye() {
+        let photos = ids(&["a", "b", "c"]);
+        let bracket = play(&photos, &[choice(0, "a", "b", "a")]);
+        assert_eq!(bracket.total, 2);
+        assert_eq!(bracket.decided, 1);
+        assert!(!bracket.complete);
+        assert_eq!(bracket.pending, vec![cull_match(1, "a", "c")]);
+
+        let bracket = play(&photos, &[choice(0, "a", "b", "a"), choice(1, "a", "c", "c")]);
+        assert!(bracket.complete);
+        assert_eq!(bracket.decided, 2);
+        assert!(bracket.pending.is_empty());
+        assert_eq!(bracket.ranking, ids(&["c", "a", "b"]));
+        assert_eq!(bracket.wins_and_losses("c"), (1, 0));
+        assert_eq!(bracket.wins_and_losses("a"), (1, 1));
+    }
+
+    #[test]
+    fn a_missing_choice_holds_the_round_back() {
+        let photos = ids(&["a", "b", "c", "d"]);
+        // The round 1 choice can't be reached until a vs b is decided, so it is ignored.
+        let bracket = play(&photos, &[choice(0, "c", "d", "d"), choice(1, "b", "d", "b")]);
+        assert_eq!(bracket.decided, 1);
+        assert!(!bracket.complete);
+        assert_eq!(bracket.pending, vec![cull_match(0, "a", "b")]);
+        assert!(!bracket.contains(1, "b", "d"));
+        assert_eq!(bracket.ranking, ids(&["a", "b", "d", "c"]));
+        assert_eq!(bracket.wins_and_losses("b"), (0, 0));
+    }
+
+    #[test]
+    fn tied_scores_fall_back_to_wins_then_session_order() {
+        let photos = ids(&["a", "b", "c"]);
+        let first = play(&photos, &[choice(0, "a", "b", "a"), choice(1, "a", "c", "a")]);
+        let second = play(&photos, &[choice(0, "a", "b", "b"), choice(1, "b", "c", "b")]);
+        assert_eq!(first.ranking, ids(&["a", "c", "b"]));
+        assert_eq!(second.ranking, ids(&["b", "c", "a"]));
+
+        let standings = combine(&photos, &[&first, &second]);
+        let order: Vec<&str> = standings.iter().map(|s| s.photo_id.as_str()).collect();
+        assert_eq!(order, vec!["a", "b", "c"]);
+        assert!(standings.iter().all(|s| s.score == 0.5));
+        assert_eq!((standings[0].wins, standings[0].losses), (2, 1));
+        assert_eq!((standings[1].wins, standings[1].losses), (2, 1));
+        assert_eq!((standings[2].wins, standings[2].losses), (0, 2));
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use dittolive_ditto::prelude::*;
+use dittolive_ditto::store::StoreObserver;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::activity::ActivityAction;
+
+use super::albums::Album;
+use super::visibility::{photos_visibility, PhotoVisibility};
+use super::{DittoRepository, ACTIVITY_COLLECTION, ACTIVITY_EVENT, ACTIVITY_VISIBILITY_FILTER};
+
+/// Append-only apart from visibility, so concurrent peers only ever add documents. An entry is
+/// only as visible as the photos it targets were when it was logged; entries about an album are
+/// rewritten to follow the album's visibility when that changes (see `set_album_visibility`).
+#[derive(Debug, Serialize, Deserialize)]
+struct ActivityDocument {
+    _id: String,
+    actor_peer_key: String,
+    actor_name: String,
+    action: ActivityAction,
+    targets: Vec<String>,
+    #[serde(default)]
+    detail: Option<String>,
+    at: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActivityEntry {
+    pub id: String,
+    pub actor_peer_key: String,
+    /// Presence display name of the actor at the time of the change.
+    pub actor_name: String,
+    pub action: ActivityAction,
+    /// IDs of the photos, albums, comments etc. the change touched.
+    pub targets: Vec<String>,
+    pub detail: Option<String>,
+    pub at: String,
+}
+
+impl From<ActivityDocument> for ActivityEntry {
+    fn from(doc: ActivityDocument) -> Self {
+        ActivityEntry {
+            id: doc._id,
+            actor_peer_key: doc.actor_peer_key,
+            actor_name: doc.actor_name,
+            action: doc.action,
+            targets: doc.targets,
+            detail: doc.detail,
+            at: doc.at,
+        }
+    }
+}
+
+impl DittoRepository {
+    /// Newest first. `target` narrows the log to entries touching one photo, album, etc.
+    pub async fn get_activity(
+        &self,
+        target: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<ActivityEntry>, String> {
+        let filter = if target.is_some() {
+            " AND array_contains(targets, :target)"
+        } else {
+            ""
+        };
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!(
+                    "SELECT * FROM {ACTIVITY_COLLECTION} WHERE ({ACTIVITY_VISIBILITY_FILTER}){filter} ORDER BY at DESC LIMIT {limit} OFFSET {offset}"
+                ),
+                serde_json::json!({ "target": target, "peer": self.local_peer_key() }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query activity: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<ActivityDocument>().ok())
+            .map(ActivityEntry::from)
+            .collect())
+    }
+
+    /// The change itself has already been made, so failing to log it is reported but not returned.
+    pub(super) async fn log_activity(&self, action: ActivityAction, targets: Vec<String>, detail: Option<String>) {
+        self.append_activity(action, targets, detail, PhotoVisibility::default())
+            .await;
+    }
+
+    /// Entries about a restricted album are only synced to the album's viewers.
+    pub(super) async fn log_album_activity(
+        &self,
+        album: &Album,
+        action: ActivityAction,
+        targets: Vec<String>,
+        detail: Option<String>,
+    ) {
+        let (restricted, visible_to) = album.visibility.to_fields();
+        let visibility = PhotoVisibility::for_album(&album.created_by, restricted, &visible_to);
+        self.append_activity(action, targets, detail, visibility)
+            .await;
+    }
+
+    async fn append_activity(
+        &self,
+        action: ActivityAction,
+        targets: Vec<String>,
+        detail: Option<String>,
+        visibility: PhotoVisibility,
+    ) {
+        if let Err(error) =
+            append_activity(self.ditto.as_ref(), action, targets, detail, visibility).await
+        {
+            eprintln!("{error}");
+        }
+    }
+}
+
+/// An entry is never more visible than the photos among its targets.
+pub(super) async fn append_activity(
+    ditto: &Ditto,
+    action: ActivityAction,
+    targets: Vec<String>,
+    detail: Option<String>,
+    visibility: PhotoVisibility,
+) -> Result<(), String> {
+    let visibility = visibility.intersect(photos_visibility(ditto, &targets).await?);
+    let local_peer = ditto.presence().graph().local_peer;
+    let doc = ActivityDocument {
+        _id: uuid::Uuid::new_v4().to_string(),
+        actor_name: local_peer
+            .peer_metadata
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| local_peer.device_name.clone()),
+        actor_peer_key: local_peer.peer_key_string,
+        action,
+        targets,
+        detail,
+        at: chrono::Utc::now().to_rfc3339(),
+        visibility,
+    };
+    ditto
+        .store()
+        .execute_v2((
+            format!("INSERT INTO {ACTIVITY_COLLECTION} DOCUMENTS (:doc)"),
+            serde_json::json!({ "doc": doc }),
+        ))
+        .await
+        .map_err(|e| format!("Failed to append activity: {e}"))?;
+    Ok(())
+}
+
+/// Emits entries as they arrive, local ones included, so a feed can simply prepend them.
+pub(super) fn install_activity_observer(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+) -> Result<Arc<StoreObserver>, String> {
+    let store = ditto.store();
+    let app_handle = app.clone();
+    let seen: std::sync::Mutex<Option<std::collections::HashSet<String>>> = std::sync::Mutex::new(None);
+    let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+    let query = (
+        format!("SELECT * FROM {ACTIVITY_COLLECTION} WHERE {ACTIVITY_VISIBILITY_FILTER}"),
+        serde_json::json!({ "peer": local_peer_key }),
+    );
+    store
+        .register_observer_v2(query, move |query_result| {
+            let entries: Vec<ActivityDocument> = query_result
+                .iter()
+                .filter_map(|item| item.deserialize_value::<ActivityDocument>().ok())
+                .collect();
+            let Ok(mut guard) = seen.lock() else {
+                return;
+            };
+            let Some(seen) = guard.as_mut() else {
+                *guard = Some(entries.into_iter().map(|entry| entry._id).collect());
+                return;
+            };
+            let mut new_entries: Vec<ActivityEntry> = entries
+                .into_iter()
+                .filter(|entry| seen.insert(entry._id.clone()))
+                .map(ActivityEntry::from)
+                .collect();
+            if !new_entries.is_empty() {
+                new_entries.sort_by(|a, b| b.at.cmp(&a.at));
+                if let Err(error) = app_handle.emit(ACTIVITY_EVENT, new_entries) {
+                    eprintln!("Failed to emit Activity: {error}");
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to register activity observer: {e}"))
+}
@@ -0,0 +1,561 @@
+use std::sync::Arc;
+
+use dittolive_ditto::prelude::*;
+use dittolive_ditto::store::StoreObserver;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+use crate::activity::ActivityAction;
+
+use super::visibility::{evict_hidden_documents, sync_photo_visibility, PhotoVisibility};
+use super::{DittoRepository, ACTIVITY_COLLECTION, ALBUMS_COLLECTION, ALBUMS_EVENT, ALBUM_MEMBERS_COLLECTION};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlbumDocument {
+    _id: String,
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    cover_photo_id: Option<String>,
+    created_by: String,
+    created_at: String,
+    #[serde(default)]
+    restricted: bool,
+    #[serde(default)]
+    visible_to: Vec<String>,
+}
+
+impl AlbumDocument {
+    fn is_visible_to(&self, peer_key: &str) -> bool {
+        !self.restricted
+            || self.created_by == peer_key
+            || self.visible_to.iter().any(|key| key == peer_key)
+    }
+}
+
+/// Membership lives in its own documents so peers adding photos to the same album concurrently
+/// both keep their additions; `position` orders the album.
+#[derive(Debug, Serialize, Deserialize)]
+struct AlbumMemberDocument {
+    _id: String,
+    album_id: String,
+    photo_id: String,
+    position: f64,
+    added_by: String,
+    added_at: String,
+    /// Copied from the album so the subscription can filter members without a join.
+    #[serde(default)]
+    album_created_by: String,
+    #[serde(default)]
+    restricted: bool,
+    #[serde(default)]
+    visible_to: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum AlbumVisibility {
+    /// Every peer in the sync group.
+    Public,
+    /// Only the creating peer.
+    Private,
+    /// The creating peer and the listed peer keys.
+    Shared { peer_keys: Vec<String> },
+}
+
+impl AlbumVisibility {
+    fn from_fields(restricted: bool, visible_to: &[String]) -> Self {
+        match (restricted, visible_to.is_empty()) {
+            (false, _) => AlbumVisibility::Public,
+            (true, true) => AlbumVisibility::Private,
+            (true, false) => AlbumVisibility::Shared {
+                peer_keys: visible_to.to_vec(),
+            },
+        }
+    }
+
+    pub(super) fn to_fields(&self) -> (bool, Vec<String>) {
+        match self {
+            AlbumVisibility::Public => (false, Vec::new()),
+            AlbumVisibility::Private => (true, Vec::new()),
+            AlbumVisibility::Shared { peer_keys } => {
+                let mut peer_keys = peer_keys.clone();
+                peer_keys.sort();
+                peer_keys.dedup();
+                (true, peer_keys)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// The chosen cover, or the first photo when none was chosen.
+    pub cover_photo_id: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub visibility: AlbumVisibility,
+    pub photo_ids: Vec<String>,
+}
+
+impl DittoRepository {
+    pub async fn get_albums(&self) -> Result<Vec<Album>, String> {
+        query_albums(self.ditto.as_ref()).await
+    }
+
+    pub async fn get_album(&self, id: &str) -> Result<Album, String> {
+        query_albums(self.ditto.as_ref())
+            .await?
+            .into_iter()
+            .find(|album| album.id == id)
+            .ok_or_else(|| format!("Album {id} not found"))
+    }
+
+    pub async fn create_album(
+        &self,
+        name: &str,
+        description: &str,
+        visibility: AlbumVisibility,
+    ) -> Result<Album, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Album name is empty".to_string());
+        }
+        let (restricted, visible_to) = visibility.to_fields();
+        let doc = AlbumDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: description.trim().to_string(),
+            cover_photo_id: None,
+            created_by: self.local_peer_key(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            restricted,
+            visible_to,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {ALBUMS_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to create album: {e}"))?;
+        let album = self.get_album(&doc._id).await?;
+        self.log_album_activity(&album, ActivityAction::CreateAlbum, vec![doc._id], Some(doc.name))
+            .await;
+        Ok(album)
+    }
+
+    /// Only the fields that are `Some` change; an empty cover ID clears the chosen cover.
+    pub async fn update_album(
+        &self,
+        id: &str,
+        name: Option<String>,
+        description: Option<String>,
+        cover_photo_id: Option<String>,
+    ) -> Result<Album, String> {
+        let album = self.get_album(id).await?;
+        let name = match name {
+            Some(name) if name.trim().is_empty() => return Err("Album name is empty".to_string()),
+            Some(name) => name.trim().to_string(),
+            None => album.name,
+        };
+        let description = description
+            .map(|d| d.trim().to_string())
+            .unwrap_or(album.description);
+        let cover_photo_id = match cover_photo_id {
+            Some(cover) if cover.is_empty() => None,
+            Some(cover) if !album.photo_ids.contains(&cover) => {
+                return Err(format!("Photo {cover} is not in album {id}"))
+            }
+            Some(cover) => Some(cover),
+            None => self.get_album_cover(id).await?,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!(
+                    "UPDATE {ALBUMS_COLLECTION} SET name = :name, description = :description, cover_photo_id = :cover_photo_id WHERE _id = :id"
+                ),
+                serde_json::json!({
+                    "name": name,
+                    "description": description,
+                    "cover_photo_id": cover_photo_id,
+                    "id": id,
+                }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update album: {e}"))?;
+        let album = self.get_album(id).await?;
+        self.log_album_activity(&album, ActivityAction::UpdateAlbum, vec![id.to_string()], Some(name))
+            .await;
+        Ok(album)
+    }
+
+    /// Only the creator decides who sees an album. Member documents carry a copy of the
+    /// visibility so they follow the album in and out of other peers' subscriptions.
+    pub async fn set_album_visibility(
+        &self,
+        id: &str,
+        visibility: AlbumVisibility,
+    ) -> Result<Album, String> {
+        let album = self.get_album(id).await?;
+        if album.created_by != self.local_peer_key() {
+            return Err("Only the album creator can change its visibility".to_string());
+        }
+        let (restricted, visible_to) = visibility.to_fields();
+        let args = serde_json::json!({ "restricted": restricted, "visible_to": visible_to, "id": id });
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("UPDATE {ALBUMS_COLLECTION} SET restricted = :restricted, visible_to = :visible_to WHERE _id = :id"),
+                args.clone(),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update album visibility: {e}"))?;
+        store
+            .execute_v2((
+                format!("UPDATE {ALBUM_MEMBERS_COLLECTION} SET restricted = :restricted, visible_to = :visible_to WHERE album_id = :id"),
+                args,
+            ))
+            .await
+            .map_err(|e| format!("Failed to update album member visibility: {e}"))?;
+        sync_photo_visibility(self.ditto.as_ref(), None).await?;
+        // The album's history follows it, so making it private also hides what happened to it.
+        let entry_visibility = PhotoVisibility::for_album(&album.created_by, restricted, &visible_to);
+        store
+            .execute_v2((
+                format!(
+                    "UPDATE {ACTIVITY_COLLECTION} SET restricted = :restricted, visible_to = :visible_to WHERE array_contains(targets, :id)"
+                ),
+                serde_json::json!({
+                    "restricted": entry_visibility.restricted,
+                    "visible_to": entry_visibility.visible_to,
+                    "id": id,
+                }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update album activity visibility: {e}"))?;
+        let detail = serde_json::to_value(&visibility)
+            .ok()
+            .and_then(|value| value.get("kind").and_then(|kind| kind.as_str()).map(str::to_string));
+        let album = self.get_album(id).await?;
+        self.log_album_activity(&album, ActivityAction::SetAlbumVisibility, vec![id.to_string()], detail)
+            .await;
+        Ok(album)
+    }
+
+    /// The explicitly chosen cover, without the first-photo fallback.
+    async fn get_album_cover(&self, id: &str) -> Result<Option<String>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {ALBUMS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query album: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<AlbumDocument>().ok())
+            .next()
+            .and_then(|doc| doc.cover_photo_id))
+    }
+
+    pub async fn delete_album(&self, id: &str) -> Result<(), String> {
+        let album = self.get_album(id).await?;
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("DELETE FROM {ALBUM_MEMBERS_COLLECTION} WHERE album_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete album members: {e}"))?;
+        // While the album still exists, so its photos drop it from their visibility.
+        sync_photo_visibility(self.ditto.as_ref(), None).await?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {ALBUMS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete album: {e}"))?;
+        self.log_album_activity(&album, ActivityAction::DeleteAlbum, vec![id.to_string()], None)
+            .await;
+        Ok(())
+    }
+
+    /// Appends photos that aren't in the album yet, in the given order.
+    pub async fn add_photos_to_album(
+        &self,
+        album_id: &str,
+        photo_ids: Vec<String>,
+    ) -> Result<Album, String> {
+        let album = self.get_album(album_id).await?;
+        let members = query_album_members(self.ditto.as_ref(), Some(album_id)).await?;
+        let mut next_position = members
+            .iter()
+            .map(|member| member.position)
+            .fold(0.0, f64::max)
+            + 1.0;
+        let added_by = self.local_peer_key();
+        let added_at = chrono::Utc::now().to_rfc3339();
+        let store = self.ditto.store();
+        let (restricted, visible_to) = album.visibility.to_fields();
+        let scope: std::collections::HashSet<String> = photo_ids.iter().cloned().collect();
+        let mut seen: std::collections::HashSet<String> = album.photo_ids.iter().cloned().collect();
+        let mut added = vec![album_id.to_string()];
+        for photo_id in photo_ids {
+            if !seen.insert(photo_id.clone()) {
+                continue;
+            }
+            added.push(photo_id.clone());
+            let doc = AlbumMemberDocument {
+                _id: format!("{album_id}:{photo_id}"),
+                album_id: album_id.to_string(),
+                photo_id,
+                position: next_position,
+                added_by: added_by.clone(),
+                added_at: added_at.clone(),
+                album_created_by: album.created_by.clone(),
+                restricted,
+                visible_to: visible_to.clone(),
+            };
+            next_position += 1.0;
+            store
+                .execute_v2((
+                    format!("INSERT INTO {ALBUM_MEMBERS_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                    serde_json::json!({ "doc": doc }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to add photo to album: {e}"))?;
+        }
+        sync_photo_visibility(self.ditto.as_ref(), Some(&scope)).await?;
+        if added.len() > 1 {
+            self.log_album_activity(&album, ActivityAction::AddToAlbum, added, None)
+                .await;
+        }
+        self.get_album(album_id).await
+    }
+
+    pub async fn remove_photos_from_album(
+        &self,
+        album_id: &str,
+        photo_ids: Vec<String>,
+    ) -> Result<Album, String> {
+        let store = self.ditto.store();
+        for photo_id in &photo_ids {
+            store
+                .execute_v2((
+                    format!("DELETE FROM {ALBUM_MEMBERS_COLLECTION} WHERE _id = :id"),
+                    serde_json::json!({ "id": format!("{album_id}:{photo_id}") }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to remove photo from album: {e}"))?;
+        }
+        let scope: std::collections::HashSet<String> = photo_ids.iter().cloned().collect();
+        sync_photo_visibility(self.ditto.as_ref(), Some(&scope)).await?;
+        if self
+            .get_album_cover(album_id)
+            .await?
+            .is_some_and(|cover| photo_ids.contains(&cover))
+        {
+            self.update_album(album_id, None, None, Some(String::new()))
+                .await?;
+        }
+        let album = self.get_album(album_id).await?;
+        let targets = std::iter::once(album_id.to_string())
+            .chain(photo_ids)
+            .collect();
+        self.log_album_activity(&album, ActivityAction::RemoveFromAlbum, targets, None)
+            .await;
+        Ok(album)
+    }
+
+    /// `photo_ids` is the new order. Members left out keep their relative order after the listed ones.
+    pub async fn reorder_album(
+        &self,
+        album_id: &str,
+        photo_ids: Vec<String>,
+    ) -> Result<Album, String> {
+        let album = self.get_album(album_id).await?;
+        let mut order: Vec<String> = photo_ids
+            .into_iter()
+            .filter(|id| album.photo_ids.contains(id))
+            .collect();
+        for id in &album.photo_ids {
+            if !order.contains(id) {
+                order.push(id.clone());
+            }
+        }
+        let members = query_album_members(self.ditto.as_ref(), Some(album_id)).await?;
+        let store = self.ditto.store();
+        for (idx, photo_id) in order.iter().enumerate() {
+            let position = (idx + 1) as f64;
+            let unchanged = members
+                .iter()
+                .any(|member| member.photo_id == *photo_id && member.position == position);
+            if unchanged {
+                continue;
+            }
+            store
+                .execute_v2((
+                    format!("UPDATE {ALBUM_MEMBERS_COLLECTION} SET position = :position WHERE _id = :id"),
+                    serde_json::json!({ "position": position, "id": format!("{album_id}:{photo_id}") }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to reorder album: {e}"))?;
+        }
+        self.log_album_activity(&album, ActivityAction::ReorderAlbum, vec![album_id.to_string()], None)
+            .await;
+        self.get_album(album_id).await
+    }
+}
+
+async fn query_album_members(
+    ditto: &Ditto,
+    album_id: Option<&str>,
+) -> Result<Vec<AlbumMemberDocument>, String> {
+    let result = match album_id {
+        Some(album_id) => {
+            ditto
+                .store()
+                .execute_v2((
+                    format!("SELECT * FROM {ALBUM_MEMBERS_COLLECTION} WHERE album_id = :album_id"),
+                    serde_json::json!({ "album_id": album_id }),
+                ))
+                .await
+        }
+        None => {
+            ditto
+                .store()
+                .execute_v2(format!("SELECT * FROM {ALBUM_MEMBERS_COLLECTION}"))
+                .await
+        }
+    }
+    .map_err(|e| format!("Failed to query album members: {e}"))?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<AlbumMemberDocument>().ok())
+        .collect())
+}
+
+/// What each album contributes to the visibility of the photos in it, keyed by album ID.
+pub(super) async fn query_album_visibility(
+    ditto: &Ditto,
+) -> Result<std::collections::HashMap<String, PhotoVisibility>, String> {
+    let result = ditto
+        .store()
+        .execute_v2(format!("SELECT * FROM {ALBUMS_COLLECTION}"))
+        .await
+        .map_err(|e| format!("Failed to query Ditto albums: {e}"))?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<AlbumDocument>().ok())
+        .map(|doc| {
+            let visibility = PhotoVisibility::for_album(&doc.created_by, doc.restricted, &doc.visible_to);
+            (doc._id, visibility)
+        })
+        .collect())
+}
+
+/// The IDs of the albums each photo is in, keyed by photo ID.
+pub(super) async fn query_album_memberships(
+    ditto: &Ditto,
+) -> Result<std::collections::HashMap<String, Vec<String>>, String> {
+    let mut memberships: std::collections::HashMap<String, Vec<String>> =
+        std::collections::HashMap::new();
+    for member in query_album_members(ditto, None).await? {
+        memberships.entry(member.photo_id).or_default().push(member.album_id);
+    }
+    Ok(memberships)
+}
+
+/// Albums this peer may see, with their members in order; ties (concurrent appends) fall back
+/// to when they were added. Albums synced before they were restricted are filtered out here too.
+async fn query_albums(ditto: &Ditto) -> Result<Vec<Album>, String> {
+    let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+    let result = ditto
+        .store()
+        .execute_v2(format!("SELECT * FROM {ALBUMS_COLLECTION} ORDER BY created_at ASC"))
+        .await
+        .map_err(|e| format!("Failed to query Ditto albums: {e}"))?;
+    let mut members: std::collections::HashMap<String, Vec<AlbumMemberDocument>> =
+        std::collections::HashMap::new();
+    for member in query_album_members(ditto, None).await? {
+        members.entry(member.album_id.clone()).or_default().push(member);
+    }
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<AlbumDocument>().ok())
+        .filter(|doc| doc.is_visible_to(&local_peer_key))
+        .map(|doc| {
+            let mut album_members = members.remove(&doc._id).unwrap_or_default();
+            album_members.sort_by(|a, b| {
+                a.position
+                    .total_cmp(&b.position)
+                    .then_with(|| a.added_at.cmp(&b.added_at))
+                    .then_with(|| a.photo_id.cmp(&b.photo_id))
+            });
+            let photo_ids: Vec<String> = album_members.into_iter().map(|member| member.photo_id).collect();
+            Album {
+                cover_photo_id: doc.cover_photo_id.or_else(|| photo_ids.first().cloned()),
+                id: doc._id,
+                name: doc.name,
+                description: doc.description,
+                visibility: AlbumVisibility::from_fields(doc.restricted, &doc.visible_to),
+                created_by: doc.created_by,
+                created_at: doc.created_at,
+                photo_ids,
+            }
+        })
+        .collect())
+}
+
+pub(super) async fn emit_albums_snapshot(ditto: &Ditto, app: &AppHandle) -> Result<(), String> {
+    let albums = query_albums(ditto).await?;
+    app.emit(ALBUMS_EVENT, albums)
+        .map_err(|e| format!("Failed to emit Albums: {e}"))
+}
+
+pub(super) fn install_albums_observers(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+) -> Result<Vec<Arc<StoreObserver>>, String> {
+    let app_handle = app.clone();
+    let ditto_for_task = ditto.clone();
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            while rx.try_recv().is_ok() {}
+            if let Err(error) = sync_photo_visibility(ditto_for_task.as_ref(), None).await {
+                eprintln!("{error}");
+            }
+            if let Err(error) = evict_hidden_documents(ditto_for_task.as_ref()).await {
+                eprintln!("{error}");
+            }
+            if let Err(error) = emit_albums_snapshot(ditto_for_task.as_ref(), &app_handle).await {
+                eprintln!("{error}");
+            }
+        }
+    });
+    [ALBUMS_COLLECTION, ALBUM_MEMBERS_COLLECTION]
+        .into_iter()
+        .map(|collection| {
+            let tx = tx.clone();
+            ditto
+                .store()
+                .register_observer_v2(format!("SELECT * FROM {collection}"), move |_query_result| {
+                    let _ = tx.send(());
+                })
+                .map_err(|e| format!("Failed to register {collection} observer: {e}"))
+        })
+        .collect()
+}
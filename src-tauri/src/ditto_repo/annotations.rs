@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+
+use super::visibility::PhotoVisibility;
+use super::{DittoRepository, ANNOTATIONS_COLLECTION};
+
+/// Coordinates are normalized to the image size, origin at the top-left corner.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum AnnotationShape {
+    Point { x: f32, y: f32 },
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+}
+
+impl AnnotationShape {
+    fn is_valid(&self) -> bool {
+        let unit = |v: f32| (0.0..=1.0).contains(&v);
+        match *self {
+            AnnotationShape::Point { x, y } => unit(x) && unit(y),
+            AnnotationShape::Rect { x, y, width, height } => {
+                unit(x) && unit(y) && width > 0.0 && height > 0.0 && unit(x + width) && unit(y + height)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationStatus {
+    Open,
+    Resolved,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AnnotationDocument {
+    _id: String,
+    photo_id: String,
+    author_peer_key: String,
+    author_name: String,
+    created_at: String,
+    shape: AnnotationShape,
+    text: String,
+    status: AnnotationStatus,
+    #[serde(default)]
+    resolved_by: Option<String>,
+    #[serde(default)]
+    resolved_at: Option<String>,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Annotation {
+    pub id: String,
+    pub photo_id: String,
+    pub author_peer_key: String,
+    pub author_name: String,
+    pub created_at: String,
+    pub shape: AnnotationShape,
+    pub text: String,
+    pub status: AnnotationStatus,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<String>,
+}
+
+impl From<AnnotationDocument> for Annotation {
+    fn from(doc: AnnotationDocument) -> Self {
+        Annotation {
+            id: doc._id,
+            photo_id: doc.photo_id,
+            author_peer_key: doc.author_peer_key,
+            author_name: doc.author_name,
+            created_at: doc.created_at,
+            shape: doc.shape,
+            text: doc.text,
+            status: doc.status,
+            resolved_by: doc.resolved_by,
+            resolved_at: doc.resolved_at,
+        }
+    }
+}
+
+impl DittoRepository {
+    pub async fn get_annotations(&self, photo_id: &str) -> Result<Vec<Annotation>, String> {
+        self.query_annotations(
+            "WHERE photo_id = :photo_id",
+            serde_json::json!({ "photo_id": photo_id }),
+        )
+        .await
+    }
+
+    /// Open annotations for one photo, or across the library when `photo_id` is `None`.
+    pub async fn get_unresolved_annotations(
+        &self,
+        photo_id: Option<&str>,
+    ) -> Result<Vec<Annotation>, String> {
+        match photo_id {
+            Some(photo_id) => {
+                self.query_annotations(
+                    "WHERE photo_id = :photo_id AND status = 'open'",
+                    serde_json::json!({ "photo_id": photo_id }),
+                )
+                .await
+            }
+            None => {
+                self.query_annotations("WHERE status = 'open'", serde_json::json!({}))
+                    .await
+            }
+        }
+    }
+
+    async fn query_annotations(
+        &self,
+        filter: &str,
+        args: serde_json::Value,
+    ) -> Result<Vec<Annotation>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {ANNOTATIONS_COLLECTION} {filter} ORDER BY created_at ASC"),
+                args,
+            ))
+            .await
+            .map_err(|e| format!("Failed to query annotations: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<AnnotationDocument>().ok())
+            .map(Annotation::from)
+            .collect())
+    }
+
+    pub async fn add_annotation(
+        &self,
+        photo_id: &str,
+        shape: AnnotationShape,
+        text: &str,
+    ) -> Result<Annotation, String> {
+        if !shape.is_valid() {
+            return Err("Annotation must lie within the image".to_string());
+        }
+        let doc = AnnotationDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            photo_id: photo_id.to_string(),
+            author_peer_key: self.local_peer_key(),
+            author_name: self.local_display_name(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            shape,
+            text: text.trim().to_string(),
+            status: AnnotationStatus::Open,
+            resolved_by: None,
+            resolved_at: None,
+            visibility: self.photo_visibility(photo_id).await?,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {ANNOTATIONS_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to add annotation: {e}"))?;
+        self.log_activity(
+            ActivityAction::AddAnnotation,
+            vec![doc.photo_id.clone(), doc._id.clone()],
+            None,
+        )
+        .await;
+        Ok(doc.into())
+    }
+
+    async fn get_annotation(&self, id: &str) -> Result<AnnotationDocument, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {ANNOTATIONS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query annotation: {e}"))?;
+        result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<AnnotationDocument>().ok())
+            .next()
+            .ok_or_else(|| format!("Annotation {id} not found"))
+    }
+
+    /// Anyone may resolve or reopen an annotation; the resolving peer is recorded.
+    pub async fn set_annotation_status(
+        &self,
+        id: &str,
+        status: AnnotationStatus,
+    ) -> Result<(), String> {
+        let (resolved_by, resolved_at) = match status {
+            AnnotationStatus::Resolved => (
+                Some(self.local_peer_key()),
+                Some(chrono::Utc::now().to_rfc3339()),
+            ),
+            AnnotationStatus::Open => (None, None),
+        };
+        let annotation = self.get_annotation(id).await?;
+        self.ditto
+            .store()
+            .execute_v2((
+                format!(
+                    "UPDATE {ANNOTATIONS_COLLECTION} SET status = :status, resolved_by = :resolved_by, resolved_at = :resolved_at WHERE _id = :id"
+                ),
+                serde_json::json!({
+                    "status": status,
+                    "resolved_by": resolved_by,
+                    "resolved_at": resolved_at,
+                    "id": id,
+                }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update annotation status: {e}"))?;
+        let detail = serde_json::to_value(status)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string));
+        self.log_activity(
+            ActivityAction::SetAnnotationStatus,
+            vec![annotation.photo_id, id.to_string()],
+            detail,
+        )
+        .await;
+        Ok(())
+    }
+
+    pub async fn delete_annotation(&self, id: &str) -> Result<(), String> {
+        let annotation = self.get_annotation(id).await?;
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("DELETE FROM {ANNOTATIONS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete annotation: {e}"))?;
+        self.log_activity(
+            ActivityAction::DeleteAnnotation,
+            vec![annotation.photo_id, id.to_string()],
+            None,
+        )
+        .await;
+        Ok(())
+    }
+}
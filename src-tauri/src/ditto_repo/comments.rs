@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use dittolive_ditto::prelude::*;
+use dittolive_ditto::store::StoreObserver;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::activity::ActivityAction;
+
+use super::visibility::PhotoVisibility;
+use super::{DittoRepository, COMMENTS_COLLECTION, NEW_COMMENTS_EVENT};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CommentDocument {
+    _id: String,
+    photo_id: String,
+    author_peer_key: String,
+    author_name: String,
+    created_at: String,
+    #[serde(default)]
+    edited_at: Option<String>,
+    text: String,
+    #[serde(default)]
+    reply_to: Option<String>,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Comment {
+    pub id: String,
+    pub photo_id: String,
+    pub author_peer_key: String,
+    /// Presence display name of the author when the comment was written.
+    pub author_name: String,
+    pub created_at: String,
+    pub edited_at: Option<String>,
+    pub text: String,
+    pub reply_to: Option<String>,
+}
+
+impl From<CommentDocument> for Comment {
+    fn from(doc: CommentDocument) -> Self {
+        Comment {
+            id: doc._id,
+            photo_id: doc.photo_id,
+            author_peer_key: doc.author_peer_key,
+            author_name: doc.author_name,
+            created_at: doc.created_at,
+            edited_at: doc.edited_at,
+            text: doc.text,
+            reply_to: doc.reply_to,
+        }
+    }
+}
+
+impl DittoRepository {
+    /// Oldest first, so replies follow the comment they answer.
+    pub async fn get_comments(&self, photo_id: &str) -> Result<Vec<Comment>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {COMMENTS_COLLECTION} WHERE photo_id = :photo_id ORDER BY created_at ASC"),
+                serde_json::json!({ "photo_id": photo_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query comments: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<CommentDocument>().ok())
+            .map(Comment::from)
+            .collect())
+    }
+
+    async fn get_comment(&self, id: &str) -> Result<CommentDocument, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {COMMENTS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query comment: {e}"))?;
+        result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<CommentDocument>().ok())
+            .next()
+            .ok_or_else(|| format!("Comment {id} not found"))
+    }
+
+    pub async fn add_comment(
+        &self,
+        photo_id: &str,
+        text: &str,
+        reply_to: Option<String>,
+    ) -> Result<Comment, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Comment text is empty".to_string());
+        }
+        if let Some(parent_id) = reply_to.as_deref() {
+            let parent = self.get_comment(parent_id).await?;
+            if parent.photo_id != photo_id {
+                return Err(format!("Comment {parent_id} belongs to another photo"));
+            }
+        }
+        let doc = CommentDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            photo_id: photo_id.to_string(),
+            author_peer_key: self.local_peer_key(),
+            author_name: self.local_display_name(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            edited_at: None,
+            text: text.to_string(),
+            reply_to,
+            visibility: self.photo_visibility(photo_id).await?,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {COMMENTS_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to add comment: {e}"))?;
+        self.log_activity(
+            ActivityAction::AddComment,
+            vec![doc.photo_id.clone(), doc._id.clone()],
+            None,
+        )
+        .await;
+        Ok(doc.into())
+    }
+
+    /// Peers can only change their own comments.
+    pub async fn edit_comment(&self, id: &str, text: &str) -> Result<Comment, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Comment text is empty".to_string());
+        }
+        let mut doc = self.get_comment(id).await?;
+        if doc.author_peer_key != self.local_peer_key() {
+            return Err("Only the author can edit a comment".to_string());
+        }
+        let edited_at = chrono::Utc::now().to_rfc3339();
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("UPDATE {COMMENTS_COLLECTION} SET text = :text, edited_at = :edited_at WHERE _id = :id"),
+                serde_json::json!({ "text": text, "edited_at": edited_at, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to edit comment: {e}"))?;
+        doc.text = text.to_string();
+        doc.edited_at = Some(edited_at);
+        self.log_activity(
+            ActivityAction::EditComment,
+            vec![doc.photo_id.clone(), doc._id.clone()],
+            None,
+        )
+        .await;
+        Ok(doc.into())
+    }
+
+    pub async fn delete_comment(&self, id: &str) -> Result<(), String> {
+        let doc = self.get_comment(id).await?;
+        if doc.author_peer_key != self.local_peer_key() {
+            return Err("Only the author can delete a comment".to_string());
+        }
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("DELETE FROM {COMMENTS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete comment: {e}"))?;
+        self.log_activity(ActivityAction::DeleteComment, vec![doc.photo_id, doc._id], None)
+            .await;
+        Ok(())
+    }
+}
+
+/// Emits comments from other peers as they sync in. Comments present when the app starts, and
+/// this peer's own comments, are not announced.
+pub(super) fn install_comments_observer(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+) -> Result<Arc<StoreObserver>, String> {
+    let store = ditto.store();
+    let app_handle = app.clone();
+    let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+    let seen: std::sync::Mutex<Option<std::collections::HashSet<String>>> = std::sync::Mutex::new(None);
+    let query = format!("SELECT * FROM {COMMENTS_COLLECTION}");
+    store
+        .register_observer_v2(query, move |query_result| {
+            let comments: Vec<CommentDocument> = query_result
+                .iter()
+                .filter_map(|item| item.deserialize_value::<CommentDocument>().ok())
+                .collect();
+            let Ok(mut guard) = seen.lock() else {
+                return;
+            };
+            let Some(seen) = guard.as_mut() else {
+                *guard = Some(comments.into_iter().map(|comment| comment._id).collect());
+                return;
+            };
+            let new_comments: Vec<Comment> = comments
+                .into_iter()
+                .filter(|comment| seen.insert(comment._id.clone()))
+                .filter(|comment| comment.author_peer_key != local_peer_key)
+                .map(Comment::from)
+                .collect();
+            if !new_comments.is_empty() {
+                if let Err(error) = app_handle.emit(NEW_COMMENTS_EVENT, new_comments) {
+                    eprintln!("Failed to emit NewComments: {error}");
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to register comments observer: {e}"))
+}
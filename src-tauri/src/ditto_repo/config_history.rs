@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+
+use super::visibility::PhotoVisibility;
+use super::{DittoRepository, PhotoConfig, CONFIG_HISTORY_COLLECTION, PHOTOS_COLLECTION};
+
+/// History entries are never changed and form a tree through `parent_id`: undo moves the photo
+/// to the parent of its current entry, redo to the newest child. Concurrent edits from two
+/// peers simply become siblings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ConfigVersionDocument {
+    _id: String,
+    photo_id: String,
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default)]
+    config: Option<PhotoConfig>,
+    author_peer_key: String,
+    author_name: String,
+    created_at: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigVersion {
+    pub id: String,
+    /// 1-based position in the photo's history: parents before their children, siblings oldest first.
+    pub version: usize,
+    pub parent_id: Option<String>,
+    pub config: Option<PhotoConfig>,
+    pub author_peer_key: String,
+    pub author_name: String,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigHistory {
+    pub photo_id: String,
+    pub current_version_id: Option<String>,
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub versions: Vec<ConfigVersion>,
+}
+
+impl DittoRepository {
+    /// Every change is recorded as a new history entry on top of the current one.
+    pub async fn update_photo_config(
+        &self,
+        id: &str,
+        config: PhotoConfig,
+    ) -> Result<(), String> {
+        let photo = self
+            .get_photo(id)
+            .await?
+            .ok_or_else(|| format!("Photo {id} not found"))?;
+        let parent_id = match photo.config_version_id {
+            Some(current) => current,
+            // The first edit also records what the photo looked like before it, so it can be undone.
+            None => self.append_config_version(id, None, photo.config).await?._id,
+        };
+        let version = self
+            .append_config_version(id, Some(parent_id), Some(config))
+            .await?;
+        self.apply_config_version(&version).await?;
+        self.log_activity(ActivityAction::EditConfig, vec![id.to_string()], None)
+            .await;
+        Ok(())
+    }
+
+    pub async fn get_config_history(&self, photo_id: &str) -> Result<ConfigHistory, String> {
+        let (versions, current) = self.config_history_position(photo_id).await?;
+        let current = current.map(|idx| &versions[idx]);
+        let current_version_id = current.map(|version| version._id.clone());
+        let can_undo = current.is_some_and(|version| version.parent_id.is_some());
+        let can_redo = current.is_some_and(|version| newest_child(&versions, &version._id).is_some());
+        Ok(ConfigHistory {
+            photo_id: photo_id.to_string(),
+            current_version_id,
+            can_undo,
+            can_redo,
+            versions: versions
+                .into_iter()
+                .enumerate()
+                .map(|(idx, doc)| ConfigVersion {
+                    id: doc._id,
+                    version: idx + 1,
+                    parent_id: doc.parent_id,
+                    config: doc.config,
+                    author_peer_key: doc.author_peer_key,
+                    author_name: doc.author_name,
+                    created_at: doc.created_at,
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn undo_photo_config(&self, photo_id: &str) -> Result<ConfigHistory, String> {
+        let (versions, current) = self.config_history_position(photo_id).await?;
+        let current = current.map(|idx| &versions[idx]);
+        let parent = current
+            .and_then(|current| current.parent_id.as_deref())
+            .and_then(|parent_id| versions.iter().find(|version| version._id == parent_id))
+            .ok_or_else(|| "Nothing to undo".to_string())?;
+        self.apply_config_version(parent).await?;
+        self.log_activity(
+            ActivityAction::EditConfig,
+            vec![photo_id.to_string()],
+            Some("undo".to_string()),
+        )
+        .await;
+        self.get_config_history(photo_id).await
+    }
+
+    pub async fn redo_photo_config(&self, photo_id: &str) -> Result<ConfigHistory, String> {
+        let (versions, current) = self.config_history_position(photo_id).await?;
+        let current = current.map(|idx| &versions[idx]);
+        let child = current
+            .and_then(|current| newest_child(&versions, &current._id))
+            .ok_or_else(|| "Nothing to redo".to_string())?;
+        self.apply_config_version(child).await?;
+        self.log_activity(
+            ActivityAction::EditConfig,
+            vec![photo_id.to_string()],
+            Some("redo".to_string()),
+        )
+        .await;
+        self.get_config_history(photo_id).await
+    }
+
+    /// Reverting adds a copy of the old version on top, so the revert itself can be undone.
+    pub async fn revert_photo_config(
+        &self,
+        photo_id: &str,
+        version_id: &str,
+    ) -> Result<ConfigHistory, String> {
+        let (versions, current) = self.config_history_position(photo_id).await?;
+        let current = current.map(|idx| &versions[idx]);
+        let target = versions
+            .iter()
+            .find(|version| version._id == version_id)
+            .ok_or_else(|| format!("Version {version_id} not found for photo {photo_id}"))?;
+        let version = self
+            .append_config_version(
+                photo_id,
+                current.map(|current| current._id.clone()),
+                target.config.clone(),
+            )
+            .await?;
+        self.apply_config_version(&version).await?;
+        let number = versions
+            .iter()
+            .position(|version| version._id == version_id)
+            .map_or(0, |idx| idx + 1);
+        self.log_activity(
+            ActivityAction::EditConfig,
+            vec![photo_id.to_string()],
+            Some(format!("revert to version {number}")),
+        )
+        .await;
+        self.get_config_history(photo_id).await
+    }
+
+    /// The photo's history in version order, and the position of the entry it currently shows.
+    async fn config_history_position(
+        &self,
+        photo_id: &str,
+    ) -> Result<(Vec<ConfigVersionDocument>, Option<usize>), String> {
+        let photo = self
+            .get_photo(photo_id)
+            .await?
+            .ok_or_else(|| format!("Photo {photo_id} not found"))?;
+        let versions = self.query_config_versions(photo_id).await?;
+        let current = photo
+            .config_version_id
+            .as_deref()
+            .and_then(|id| versions.iter().position(|version| version._id == id));
+        Ok((versions, current))
+    }
+
+    /// In version order; see `config_tree_order`.
+    async fn query_config_versions(&self, photo_id: &str) -> Result<Vec<ConfigVersionDocument>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {CONFIG_HISTORY_COLLECTION} WHERE photo_id = :photo_id"),
+                serde_json::json!({ "photo_id": photo_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query config history: {e}"))?;
+        Ok(config_tree_order(
+            result
+                .iter()
+                .filter_map(|item| item.deserialize_value::<ConfigVersionDocument>().ok())
+                .collect(),
+        ))
+    }
+
+    async fn append_config_version(
+        &self,
+        photo_id: &str,
+        parent_id: Option<String>,
+        config: Option<PhotoConfig>,
+    ) -> Result<ConfigVersionDocument, String> {
+        let doc = ConfigVersionDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            photo_id: photo_id.to_string(),
+            parent_id,
+            config,
+            author_peer_key: self.local_peer_key(),
+            author_name: self.local_display_name(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            visibility: self.photo_visibility(photo_id).await?,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {CONFIG_HISTORY_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to record config history: {e}"))?;
+        Ok(doc)
+    }
+
+    async fn apply_config_version(&self, version: &ConfigVersionDocument) -> Result<(), String> {
+        self.ditto
+            .store()
+            .execute_v2((
+                format!(
+                    "UPDATE {PHOTOS_COLLECTION} SET config = :config, config_version_id = :version_id WHERE _id = :id"
+                ),
+                serde_json::json!({
+                    "config": version.config,
+                    "version_id": version._id,
+                    "id": version.photo_id,
+                }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo config: {e}"))?;
+        Ok(())
+    }
+}
+
+/// Walks the history tree depth first, so every entry comes after its parent whatever the peers'
+/// clocks say; `created_at` only orders siblings. An entry whose parent hasn't synced here yet
+/// starts a tree of its own.
+fn config_tree_order(mut versions: Vec<ConfigVersionDocument>) -> Vec<ConfigVersionDocument> {
+    versions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a._id.cmp(&b._id)));
+    let ids: std::collections::HashSet<String> =
+        versions.iter().map(|version| version._id.clone()).collect();
+    let mut children: std::collections::HashMap<Option<String>, Vec<ConfigVersionDocument>> =
+        std::collections::HashMap::new();
+    for version in versions {
+        let parent_id = version.parent_id.clone().filter(|id| ids.contains(id));
+        children.entry(parent_id).or_default().push(version);
+    }
+
+    let mut ordered = Vec::with_capacity(ids.len());
+    let mut stack: Vec<ConfigVersionDocument> =
+        children.remove(&None).unwrap_or_default().into_iter().rev().collect();
+    while let Some(version) = stack.pop() {
+        if let Some(siblings) = children.remove(&Some(version._id.clone())) {
+            stack.extend(siblings.into_iter().rev());
+        }
+        ordered.push(version);
+    }
+    ordered
+}
+
+/// Redo follows the most recent branch when concurrent edits left several children.
+fn newest_child<'a>(
+    versions: &'a [ConfigVersionDocument],
+    parent_id: &str,
+) -> Option<&'a ConfigVersionDocument> {
+    versions
+        .iter()
+        .rev()
+        .find(|version| version.parent_id.as_deref() == Some(parent_id))
+}
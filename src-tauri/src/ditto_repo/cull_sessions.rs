@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+use crate::culling::{combine, play, Bracket, CullChoice, CullStanding};
+
+use super::visibility::PhotoVisibility;
+use super::{DittoRepository, CULL_CHOICES_COLLECTION, CULL_SESSIONS_COLLECTION};
+
+/// The bracket order is fixed at creation, so every peer replays the same pairings.
+#[derive(Debug, Serialize, Deserialize)]
+struct CullSessionDocument {
+    _id: String,
+    name: String,
+    photo_ids: Vec<String>,
+    created_by: String,
+    created_at: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+/// One document per session, peer and match, so each peer's bracket merges without conflicts.
+#[derive(Debug, Serialize, Deserialize)]
+struct CullChoiceDocument {
+    _id: String,
+    session_id: String,
+    peer_key: String,
+    round: u32,
+    left_id: String,
+    right_id: String,
+    /// Both sides of the match, so the choice shares their visibility.
+    #[serde(default)]
+    photo_ids: Vec<String>,
+    winner_id: String,
+    chosen_at: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CullSession {
+    pub id: String,
+    pub name: String,
+    pub photo_ids: Vec<String>,
+    pub created_by: String,
+    pub created_at: String,
+}
+
+impl From<CullSessionDocument> for CullSession {
+    fn from(doc: CullSessionDocument) -> Self {
+        CullSession {
+            id: doc._id,
+            name: doc.name,
+            photo_ids: doc.photo_ids,
+            created_by: doc.created_by,
+            created_at: doc.created_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CullProgress {
+    pub session_id: String,
+    pub peer_key: String,
+    #[serde(flatten)]
+    pub bracket: Bracket,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CullResults {
+    pub session: CullSession,
+    /// Every peer that has made at least one choice, plus this one.
+    pub peers: Vec<CullProgress>,
+    pub ranking: Vec<CullStanding>,
+}
+
+impl DittoRepository {
+    pub async fn get_cull_sessions(&self) -> Result<Vec<CullSession>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2(format!("SELECT * FROM {CULL_SESSIONS_COLLECTION} ORDER BY created_at DESC"))
+            .await
+            .map_err(|e| format!("Failed to query cull sessions: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<CullSessionDocument>().ok())
+            .map(CullSession::from)
+            .collect())
+    }
+
+    pub async fn get_cull_session(&self, id: &str) -> Result<CullSession, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {CULL_SESSIONS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query cull session: {e}"))?;
+        result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<CullSessionDocument>().ok())
+            .map(CullSession::from)
+            .next()
+            .ok_or_else(|| format!("Cull session {id} not found"))
+    }
+
+    pub async fn create_cull_session(
+        &self,
+        name: &str,
+        photo_ids: Vec<String>,
+    ) -> Result<CullSession, String> {
+        let mut seen = std::collections::HashSet::new();
+        let photo_ids: Vec<String> = photo_ids
+            .into_iter()
+            .filter(|id| seen.insert(id.clone()))
+            .collect();
+        if photo_ids.len() < 2 {
+            return Err("A cull session needs at least two photos".to_string());
+        }
+        let name = name.trim();
+        let visibility = self.photos_visibility(&photo_ids).await?;
+        let doc = CullSessionDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            name: if name.is_empty() { "Cull".to_string() } else { name.to_string() },
+            photo_ids,
+            created_by: self.local_peer_key(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            visibility,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {CULL_SESSIONS_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to create cull session: {e}"))?;
+        self.log_activity(
+            ActivityAction::CreateCullSession,
+            vec![doc._id.clone()],
+            Some(doc.name.clone()),
+        )
+        .await;
+        Ok(CullSession::from(doc))
+    }
+
+    pub async fn delete_cull_session(&self, id: &str) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("DELETE FROM {CULL_CHOICES_COLLECTION} WHERE session_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete cull choices: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {CULL_SESSIONS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete cull session: {e}"))?;
+        self.log_activity(ActivityAction::DeleteCullSession, vec![id.to_string()], None)
+            .await;
+        Ok(())
+    }
+
+    /// This peer's bracket, including the next matches to decide.
+    pub async fn get_cull_progress(&self, session_id: &str) -> Result<CullProgress, String> {
+        let session = self.get_cull_session(session_id).await?;
+        let peer_key = self.local_peer_key();
+        let choices = self.query_cull_choices(session_id).await?;
+        Ok(cull_progress(&session, &peer_key, &choices))
+    }
+
+    /// Records this peer's pick for a match it has reached. Re-deciding an earlier match is
+    /// allowed; later choices that no longer line up with the bracket are simply ignored.
+    pub async fn record_cull_choice(
+        &self,
+        session_id: &str,
+        round: u32,
+        left_id: &str,
+        right_id: &str,
+        winner_id: &str,
+    ) -> Result<CullProgress, String> {
+        if winner_id != left_id && winner_id != right_id {
+            return Err("The winner must be one of the compared photos".to_string());
+        }
+        let session = self.get_cull_session(session_id).await?;
+        let peer_key = self.local_peer_key();
+        let choices = self.query_cull_choices(session_id).await?;
+        if !cull_progress(&session, &peer_key, &choices)
+            .bracket
+            .contains(round, left_id, right_id)
+        {
+            return Err("That match is not part of this peer's bracket".to_string());
+        }
+        let photo_ids = vec![left_id.to_string(), right_id.to_string()];
+        let visibility = self.photos_visibility(&photo_ids).await?;
+        let doc = CullChoiceDocument {
+            _id: format!("{session_id}:{peer_key}:{round}:{left_id}:{right_id}"),
+            session_id: session_id.to_string(),
+            peer_key: peer_key.clone(),
+            round,
+            left_id: left_id.to_string(),
+            right_id: right_id.to_string(),
+            photo_ids,
+            winner_id: winner_id.to_string(),
+            chosen_at: chrono::Utc::now().to_rfc3339(),
+            visibility,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {CULL_CHOICES_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to record cull choice: {e}"))?;
+        self.log_activity(
+            ActivityAction::RecordCullChoice,
+            vec![session_id.to_string(), winner_id.to_string()],
+            Some(format!("round {round}")),
+        )
+        .await;
+        self.get_cull_progress(session_id).await
+    }
+
+    /// Every peer's progress and a combined ranking over the peers that have taken part.
+    pub async fn get_cull_results(&self, session_id: &str) -> Result<CullResults, String> {
+        let session = self.get_cull_session(session_id).await?;
+        let choices = self.query_cull_choices(session_id).await?;
+        let mut peer_keys: Vec<String> = choices.iter().map(|doc| doc.peer_key.clone()).collect();
+        let local_peer_key = self.local_peer_key();
+        peer_keys.push(local_peer_key.clone());
+        peer_keys.sort();
+        peer_keys.dedup();
+        let peers: Vec<CullProgress> = peer_keys
+            .iter()
+            .map(|peer_key| cull_progress(&session, peer_key, &choices))
+            .collect();
+        let participating: Vec<&Bracket> = peers
+            .iter()
+            .filter(|progress| progress.bracket.decided > 0)
+            .map(|progress| &progress.bracket)
+            .collect();
+        let ranking = combine(&session.photo_ids, &participating);
+        Ok(CullResults {
+            session,
+            peers,
+            ranking,
+        })
+    }
+
+    async fn query_cull_choices(&self, session_id: &str) -> Result<Vec<CullChoiceDocument>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {CULL_CHOICES_COLLECTION} WHERE session_id = :session_id"),
+                serde_json::json!({ "session_id": session_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query cull choices: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<CullChoiceDocument>().ok())
+            .collect())
+    }
+}
+
+fn cull_progress(
+    session: &CullSession,
+    peer_key: &str,
+    choices: &[CullChoiceDocument],
+) -> CullProgress {
+    let choices: Vec<CullChoice> = choices
+        .iter()
+        .filter(|doc| doc.peer_key == peer_key)
+        .map(|doc| CullChoice {
+            round: doc.round,
+            left_id: doc.left_id.clone(),
+            right_id: doc.right_id.clone(),
+            winner_id: doc.winner_id.clone(),
+        })
+        .collect();
+    CullProgress {
+        session_id: session.id.clone(),
+        peer_key: peer_key.to_string(),
+        bracket: play(&session.photo_ids, &choices),
+    }
+}
@@ -0,0 +1,631 @@
+use dittolive_ditto::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+use crate::faces::{DetectedFace, FaceBox};
+
+use super::visibility::PhotoVisibility;
+use super::{DittoRepository, FACE_EMBEDDINGS_COLLECTION, FACE_REGIONS_COLLECTION, PEOPLE_COLLECTION};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersonDocument {
+    _id: String,
+    name: String,
+    #[serde(default)]
+    created_by: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    merged_into: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Person {
+    pub id: String,
+    pub name: String,
+    pub created_by: Option<String>,
+    pub created_at: Option<String>,
+    pub face_count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct FaceRegionDocument {
+    _id: String,
+    photo_id: String,
+    bounding_box: FaceBox,
+    embedding_id: String,
+    #[serde(default)]
+    detected_by: Option<String>,
+    #[serde(default)]
+    cluster_id: Option<String>,
+    #[serde(default)]
+    person_id: Option<String>,
+    #[serde(default)]
+    confirmed_by: Option<String>,
+    #[serde(default)]
+    confirmed_at: Option<String>,
+    #[serde(default)]
+    rejected_person_ids: Vec<String>,
+}
+
+/// Only the detection fields, so re-indexing a photo keeps the people assignments.
+#[derive(Debug, Serialize)]
+struct FaceRegionWrite {
+    _id: String,
+    photo_id: String,
+    bounding_box: FaceBox,
+    embedding_id: String,
+    detected_by: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FaceRegion {
+    pub id: String,
+    pub photo_id: String,
+    pub bounding_box: FaceBox,
+    pub embedding_id: String,
+    pub detected_by: Option<String>,
+    pub cluster_id: Option<String>,
+    pub person_id: Option<String>,
+    pub confirmed_by: Option<String>,
+    pub confirmed_at: Option<String>,
+    pub rejected_person_ids: Vec<String>,
+}
+
+impl From<FaceRegionDocument> for FaceRegion {
+    fn from(doc: FaceRegionDocument) -> Self {
+        FaceRegion {
+            id: doc._id,
+            photo_id: doc.photo_id,
+            bounding_box: doc.bounding_box,
+            embedding_id: doc.embedding_id,
+            detected_by: doc.detected_by,
+            cluster_id: doc.cluster_id,
+            person_id: doc.person_id,
+            confirmed_by: doc.confirmed_by,
+            confirmed_at: doc.confirmed_at,
+            rejected_person_ids: doc.rejected_person_ids,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FaceEmbeddingDocument {
+    _id: String,
+    #[serde(default)]
+    photo_id: String,
+    vector: Vec<f32>,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FaceCluster {
+    pub cluster_id: String,
+    pub region_ids: Vec<String>,
+    pub photo_ids: Vec<String>,
+}
+
+impl DittoRepository {
+    /// Faces of a photo as analyzed by whichever peer holds the original.
+    pub async fn get_detected_faces(&self, photo_id: &str) -> Result<Vec<DetectedFace>, String> {
+        let store = self.ditto.store();
+        let mut faces = Vec::new();
+        for region in self.get_face_regions(Some(photo_id)).await? {
+            let result = store
+                .execute_v2((
+                    format!("SELECT * FROM {FACE_EMBEDDINGS_COLLECTION} WHERE _id = :id"),
+                    serde_json::json!({ "id": region.embedding_id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to query face embedding: {e}"))?;
+            let Some(embedding) = result
+                .iter()
+                .next()
+                .and_then(|item| item.deserialize_value::<FaceEmbeddingDocument>().ok())
+            else {
+                continue;
+            };
+            faces.push(DetectedFace {
+                bounding_box: region.bounding_box,
+                embedding: embedding.vector,
+            });
+        }
+        Ok(faces)
+    }
+
+    pub async fn get_face_regions(&self, photo_id: Option<&str>) -> Result<Vec<FaceRegion>, String> {
+        query_face_regions(self.ditto.as_ref(), photo_id).await
+    }
+
+    pub async fn store_photo_faces(
+        &self,
+        photo_id: &str,
+        faces: Vec<DetectedFace>,
+    ) -> Result<Vec<FaceRegion>, String> {
+        let store = self.ditto.store();
+        let detected_by = self.local_peer_key();
+        let visibility = self.photo_visibility(photo_id).await?;
+        let existing = self.get_face_regions(Some(photo_id)).await?;
+        let matched = match_existing_regions(&existing, &faces);
+        let mut region_ids = std::collections::HashSet::new();
+
+        for (face, matched_id) in faces.into_iter().zip(matched) {
+            // Reusing the ID of the region in the same place keeps its person when detection
+            // order changes between runs.
+            let region_id =
+                matched_id.unwrap_or_else(|| format!("{photo_id}:{}", uuid::Uuid::new_v4()));
+            region_ids.insert(region_id.clone());
+            let embedding = FaceEmbeddingDocument {
+                _id: region_id.clone(),
+                photo_id: photo_id.to_string(),
+                vector: face.embedding,
+                visibility: visibility.clone(),
+            };
+            store
+                .execute_v2((
+                    format!("INSERT INTO {FACE_EMBEDDINGS_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                    serde_json::json!({ "doc": embedding }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to store face embedding: {e}"))?;
+            let region = FaceRegionWrite {
+                _id: region_id.clone(),
+                photo_id: photo_id.to_string(),
+                bounding_box: face.bounding_box,
+                embedding_id: region_id,
+                detected_by: detected_by.clone(),
+                visibility: visibility.clone(),
+            };
+            store
+                .execute_v2((
+                    format!("INSERT INTO {FACE_REGIONS_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                    serde_json::json!({ "doc": region }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to store face region: {e}"))?;
+        }
+
+        for region in existing {
+            if !region_ids.contains(&region.id) {
+                self.delete_face_region(&region).await?;
+            }
+        }
+
+        self.get_face_regions(Some(photo_id)).await
+    }
+
+    pub(super) async fn delete_face_region(&self, region: &FaceRegion) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("DELETE FROM {FACE_REGIONS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": region.id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete face region: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {FACE_EMBEDDINGS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": region.embedding_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete face embedding: {e}"))?;
+        Ok(())
+    }
+
+    async fn get_face_embeddings(&self) -> Result<std::collections::HashMap<String, Vec<f32>>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2(format!("SELECT * FROM {FACE_EMBEDDINGS_COLLECTION}"))
+            .await
+            .map_err(|e| format!("Failed to query face embeddings: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<FaceEmbeddingDocument>().ok())
+            .map(|doc| (doc._id, doc.vector))
+            .collect())
+    }
+
+    pub async fn get_people(&self) -> Result<Vec<Person>, String> {
+        let regions = self.get_face_regions(None).await?;
+        let result = self
+            .ditto
+            .store()
+            .execute_v2(format!("SELECT * FROM {PEOPLE_COLLECTION}"))
+            .await
+            .map_err(|e| format!("Failed to query people: {e}"))?;
+        Ok(result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<PersonDocument>().ok())
+            .filter(|doc| doc.merged_into.is_none())
+            .map(|doc| Person {
+                face_count: regions
+                    .iter()
+                    .filter(|region| region.person_id.as_deref() == Some(doc._id.as_str()))
+                    .count(),
+                id: doc._id,
+                name: doc.name,
+                created_by: doc.created_by,
+                created_at: doc.created_at,
+            })
+            .collect())
+    }
+
+    async fn find_or_create_person(&self, name: &str) -> Result<Person, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Person name cannot be empty".to_string());
+        }
+        if let Some(person) = self
+            .get_people()
+            .await?
+            .into_iter()
+            .find(|person| person.name.eq_ignore_ascii_case(name))
+        {
+            return Ok(person);
+        }
+
+        let doc = PersonDocument {
+            _id: uuid::Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            created_by: Some(self.local_peer_key()),
+            created_at: Some(chrono::Utc::now().to_rfc3339()),
+            merged_into: None,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {PEOPLE_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to create person: {e}"))?;
+        Ok(Person {
+            id: doc._id,
+            name: doc.name,
+            created_by: doc.created_by,
+            created_at: doc.created_at,
+            face_count: 0,
+        })
+    }
+
+    /// Tentatively assigns unassigned faces to the closest confirmed person.
+    /// Suggestions stay unconfirmed (`confirmed_by` is empty) until someone accepts them.
+    pub async fn suggest_people(&self, threshold: f32) -> Result<usize, String> {
+        let regions = self.get_face_regions(None).await?;
+        let embeddings = self.get_face_embeddings().await?;
+
+        let mut references: std::collections::HashMap<&str, Vec<Vec<f32>>> =
+            std::collections::HashMap::new();
+        for region in &regions {
+            if let (Some(person_id), Some(_)) = (region.person_id.as_deref(), region.confirmed_by.as_ref()) {
+                if let Some(vector) = embeddings.get(&region.embedding_id) {
+                    references.entry(person_id).or_default().push(vector.clone());
+                }
+            }
+        }
+
+        let store = self.ditto.store();
+        let mut suggested = 0;
+        for region in regions.iter().filter(|region| region.person_id.is_none()) {
+            let Some(vector) = embeddings.get(&region.embedding_id) else {
+                continue;
+            };
+            let best = references
+                .iter()
+                .filter(|(person_id, _)| !region.rejected_person_ids.iter().any(|r| r == *person_id))
+                .filter_map(|(person_id, faces)| {
+                    crate::faces::best_match(vector, faces).map(|(_, score)| (*person_id, score))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((person_id, score)) = best else {
+                continue;
+            };
+            if score < threshold {
+                continue;
+            }
+            store
+                .execute_v2((
+                    format!("UPDATE {FACE_REGIONS_COLLECTION} SET person_id = :person_id WHERE _id = :id"),
+                    serde_json::json!({ "person_id": person_id, "id": region.id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to suggest person for face: {e}"))?;
+            suggested += 1;
+        }
+        Ok(suggested)
+    }
+
+    pub async fn cluster_faces(&self, threshold: f32) -> Result<Vec<FaceCluster>, String> {
+        let embeddings = self.get_face_embeddings().await?;
+        let unassigned: Vec<FaceRegion> = self
+            .get_face_regions(None)
+            .await?
+            .into_iter()
+            .filter(|region| region.person_id.is_none() && embeddings.contains_key(&region.embedding_id))
+            .collect();
+        let vectors: Vec<Vec<f32>> = unassigned
+            .iter()
+            .map(|region| embeddings[&region.embedding_id].clone())
+            .collect();
+        let labels = crate::faces::cluster_embeddings(&vectors, threshold);
+
+        // Name each cluster after its smallest region id so every peer derives the same id.
+        let mut cluster_names: std::collections::HashMap<usize, String> = std::collections::HashMap::new();
+        for (region, label) in unassigned.iter().zip(&labels) {
+            let name = cluster_names.entry(*label).or_insert_with(|| region.id.clone());
+            if region.id < *name {
+                *name = region.id.clone();
+            }
+        }
+
+        let store = self.ditto.store();
+        for (region, label) in unassigned.iter().zip(&labels) {
+            let cluster_id = format!("cluster:{}", cluster_names[label]);
+            if region.cluster_id.as_deref() == Some(cluster_id.as_str()) {
+                continue;
+            }
+            store
+                .execute_v2((
+                    format!("UPDATE {FACE_REGIONS_COLLECTION} SET cluster_id = :cluster_id WHERE _id = :id"),
+                    serde_json::json!({ "cluster_id": cluster_id, "id": region.id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update face cluster: {e}"))?;
+        }
+
+        self.get_face_clusters().await
+    }
+
+    pub async fn get_face_clusters(&self) -> Result<Vec<FaceCluster>, String> {
+        let mut clusters: Vec<FaceCluster> = Vec::new();
+        for region in self.get_face_regions(None).await? {
+            if region.person_id.is_some() {
+                continue;
+            }
+            let Some(cluster_id) = region.cluster_id else {
+                continue;
+            };
+            match clusters.iter_mut().find(|c| c.cluster_id == cluster_id) {
+                Some(cluster) => {
+                    cluster.region_ids.push(region.id);
+                    if !cluster.photo_ids.contains(&region.photo_id) {
+                        cluster.photo_ids.push(region.photo_id);
+                    }
+                }
+                None => clusters.push(FaceCluster {
+                    cluster_id,
+                    region_ids: vec![region.id],
+                    photo_ids: vec![region.photo_id],
+                }),
+            }
+        }
+        clusters.sort_by(|a, b| b.region_ids.len().cmp(&a.region_ids.len()));
+        Ok(clusters)
+    }
+
+    pub async fn name_face_cluster(&self, cluster_id: &str, name: &str) -> Result<Person, String> {
+        let person = self.find_or_create_person(name).await?;
+        self.ditto
+            .store()
+            .execute_v2((
+                format!(
+                    "UPDATE {FACE_REGIONS_COLLECTION} SET person_id = :person_id, confirmed_by = :confirmed_by, confirmed_at = :confirmed_at, cluster_id = NULL WHERE cluster_id = :cluster_id"
+                ),
+                serde_json::json!({
+                    "person_id": person.id,
+                    "confirmed_by": self.local_peer_key(),
+                    "confirmed_at": chrono::Utc::now().to_rfc3339(),
+                    "cluster_id": cluster_id,
+                }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to name face cluster: {e}"))?;
+        self.log_activity(
+            ActivityAction::NamePerson,
+            vec![cluster_id.to_string(), person.id.clone()],
+            Some(name.to_string()),
+        )
+        .await;
+        Ok(person)
+    }
+
+    pub async fn confirm_face_regions(
+        &self,
+        region_ids: Vec<String>,
+        person_id: &str,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        let confirmed_by = self.local_peer_key();
+        let confirmed_at = chrono::Utc::now().to_rfc3339();
+        for id in &region_ids {
+            store
+                .execute_v2((
+                    format!(
+                        "UPDATE {FACE_REGIONS_COLLECTION} SET person_id = :person_id, confirmed_by = :confirmed_by, confirmed_at = :confirmed_at, cluster_id = NULL WHERE _id = :id"
+                    ),
+                    serde_json::json!({
+                        "person_id": person_id,
+                        "confirmed_by": confirmed_by,
+                        "confirmed_at": confirmed_at,
+                        "id": id,
+                    }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to confirm face: {e}"))?;
+        }
+        self.log_activity(ActivityAction::ConfirmFaces, region_ids, Some(person_id.to_string()))
+            .await;
+        Ok(())
+    }
+
+    pub async fn merge_people(&self, source_ids: Vec<String>, target_id: &str) -> Result<(), String> {
+        let store = self.ditto.store();
+        for source_id in source_ids.iter().filter(|id| id.as_str() != target_id) {
+            store
+                .execute_v2((
+                    format!("UPDATE {FACE_REGIONS_COLLECTION} SET person_id = :target_id WHERE person_id = :source_id"),
+                    serde_json::json!({ "target_id": target_id, "source_id": source_id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to move faces to merged person: {e}"))?;
+            // Kept rather than deleted, so faces tagged offline on another peer still resolve.
+            store
+                .execute_v2((
+                    format!("UPDATE {PEOPLE_COLLECTION} SET merged_into = :target_id WHERE _id = :source_id"),
+                    serde_json::json!({ "target_id": target_id, "source_id": source_id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to merge person: {e}"))?;
+        }
+        self.log_activity(ActivityAction::MergePeople, source_ids, Some(target_id.to_string()))
+            .await;
+        Ok(())
+    }
+
+    pub async fn split_face_regions(&self, region_ids: Vec<String>) -> Result<String, String> {
+        let store = self.ditto.store();
+        let cluster_id = format!("cluster:{}", uuid::Uuid::new_v4());
+        for id in &region_ids {
+            store
+                .execute_v2((
+                    format!(
+                        "UPDATE {FACE_REGIONS_COLLECTION} SET cluster_id = :cluster_id, person_id = NULL, confirmed_by = NULL, confirmed_at = NULL WHERE _id = :id"
+                    ),
+                    serde_json::json!({ "cluster_id": cluster_id, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to split face region: {e}"))?;
+        }
+        self.log_activity(ActivityAction::SplitFaces, region_ids, Some(cluster_id.clone()))
+            .await;
+        Ok(cluster_id)
+    }
+
+    /// Detaches the faces from their (suggested or confirmed) person and never suggests that person again.
+    pub async fn reject_face_regions(&self, region_ids: Vec<String>) -> Result<(), String> {
+        let store = self.ditto.store();
+        let regions = self.get_face_regions(None).await?;
+        for id in &region_ids {
+            let Some(region) = regions.iter().find(|region| region.id == *id) else {
+                continue;
+            };
+            let mut rejected = region.rejected_person_ids.clone();
+            if let Some(person_id) = region.person_id.as_ref() {
+                if !rejected.contains(person_id) {
+                    rejected.push(person_id.clone());
+                }
+            }
+            store
+                .execute_v2((
+                    format!(
+                        "UPDATE {FACE_REGIONS_COLLECTION} SET person_id = NULL, confirmed_by = NULL, confirmed_at = NULL, cluster_id = NULL, rejected_person_ids = :rejected WHERE _id = :id"
+                    ),
+                    serde_json::json!({ "rejected": rejected, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to reject face region: {e}"))?;
+        }
+        self.log_activity(ActivityAction::RejectFaces, region_ids, None)
+            .await;
+        Ok(())
+    }
+}
+
+async fn query_face_regions(
+    ditto: &Ditto,
+    photo_id: Option<&str>,
+) -> Result<Vec<FaceRegion>, String> {
+    let store = ditto.store();
+    let result = match photo_id {
+        Some(photo_id) => store
+            .execute_v2((
+                format!("SELECT * FROM {FACE_REGIONS_COLLECTION} WHERE photo_id = :photo_id"),
+                serde_json::json!({ "photo_id": photo_id }),
+            ))
+            .await,
+        None => {
+            store
+                .execute_v2(format!("SELECT * FROM {FACE_REGIONS_COLLECTION}"))
+                .await
+        }
+    }
+    .map_err(|e| format!("Failed to query face regions: {e}"))?;
+    let merged_into = query_merged_people(ditto).await?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<FaceRegionDocument>().ok())
+        .map(|doc| {
+            let mut region = FaceRegion::from(doc);
+            region.person_id = region
+                .person_id
+                .map(|person_id| resolve_merged_person(&merged_into, person_id));
+            region
+        })
+        .collect())
+}
+
+/// Merged person ID -> the person it was merged into.
+async fn query_merged_people(
+    ditto: &Ditto,
+) -> Result<std::collections::HashMap<String, String>, String> {
+    let result = ditto
+        .store()
+        .execute_v2(format!("SELECT * FROM {PEOPLE_COLLECTION}"))
+        .await
+        .map_err(|e| format!("Failed to query merged people: {e}"))?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<PersonDocument>().ok())
+        .filter_map(|doc| Some((doc._id, doc.merged_into?)))
+        .collect())
+}
+
+/// Follows merges to the surviving person, so faces confirmed offline against a person that
+/// was merged away (possibly more than once) still count for the merge target.
+fn resolve_merged_person(
+    merged_into: &std::collections::HashMap<String, String>,
+    person_id: String,
+) -> String {
+    let mut current = person_id;
+    let mut visited = std::collections::HashSet::new();
+    while let Some(next) = merged_into.get(&current) {
+        // Concurrent merges in opposite directions can form a cycle; stop where it closes.
+        if !visited.insert(current.clone()) {
+            break;
+        }
+        current = next.clone();
+    }
+    current
+}
+
+/// Overlap at which a newly detected face is taken to be the same face as an existing region.
+const REGION_MATCH_IOU: f32 = 0.5;
+
+/// For each detected face, the ID of the existing region it replaces, if any. Pairs are
+/// matched greedily by overlap so each region is reused at most once.
+fn match_existing_regions(existing: &[FaceRegion], faces: &[DetectedFace]) -> Vec<Option<String>> {
+    let mut pairs: Vec<(f32, usize, usize)> = Vec::new();
+    for (face_idx, face) in faces.iter().enumerate() {
+        for (region_idx, region) in existing.iter().enumerate() {
+            let iou = face.bounding_box.iou(&region.bounding_box);
+            if iou >= REGION_MATCH_IOU {
+                pairs.push((iou, face_idx, region_idx));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut matched = vec![None; faces.len()];
+    let mut used = std::collections::HashSet::new();
+    for (_, face_idx, region_idx) in pairs {
+        if matched[face_idx].is_none() && used.insert(region_idx) {
+            matched[face_idx] = Some(existing[region_idx].id.clone());
+        }
+    }
+    matched
+}
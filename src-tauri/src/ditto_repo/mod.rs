@@ -0,0 +1,2135 @@
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use tokio::sync::mpsc;
+
+use dittolive_ditto::dql::QueryResult;
+use dittolive_ditto::fs::PersistentRoot;
+use dittolive_ditto::identity;
+use dittolive_ditto::prelude::*;
+use dittolive_ditto::transport::Peer;
+use dittolive_ditto::store::StoreObserver;
+use dittolive_ditto::store::attachment::{DittoAttachment, DittoAttachmentToken};
+use serde::{Deserialize, Serialize};
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Emitter, Manager};
+use base64::{engine::general_purpose, Engine as _};
+use image::GenericImageView;
+
+use crate::activity::ActivityAction;
+use crate::auto_tagging::MachineTag;
+use crate::color_palette::{color_distance, photo_palette, PaletteColor};
+use crate::perceptual_hash::{photo_hash, PerceptualHash};
+use crate::quality::{photo_quality, QualityScore};
+use crate::tags::normalize_tag;
+
+mod activity;
+mod albums;
+mod annotations;
+mod comments;
+mod config_history;
+mod cull_sessions;
+mod faces;
+mod smart_albums;
+mod stack_suggestions;
+mod visibility;
+mod votes;
+
+pub use activity::ActivityEntry;
+pub use albums::{Album, AlbumVisibility};
+pub use annotations::{Annotation, AnnotationShape, AnnotationStatus};
+pub use comments::Comment;
+pub use config_history::ConfigHistory;
+pub use cull_sessions::{CullProgress, CullResults, CullSession};
+pub use faces::{FaceCluster, FaceRegion, Person};
+pub use smart_albums::SmartAlbum;
+
+use activity::{append_activity, install_activity_observer};
+use albums::{emit_albums_snapshot, install_albums_observers};
+use comments::install_comments_observer;
+use smart_albums::{emit_smart_albums_snapshot, install_smart_albums_observer};
+use visibility::{evict_hidden_documents, filtered_collections, sync_photo_visibility, PhotoVisibility};
+
+const STATE_COLLECTION: &str = "app_state";
+const STATE_DOC_ID: &str = "root";
+const PHOTOS_COLLECTION: &str = "photos";
+const SMART_ALBUMS_COLLECTION: &str = "smart_albums";
+const PEOPLE_COLLECTION: &str = "people";
+const FACE_REGIONS_COLLECTION: &str = "face_regions";
+const FACE_EMBEDDINGS_COLLECTION: &str = "face_embeddings";
+const DISMISSED_STACK_SUGGESTIONS_COLLECTION: &str = "dismissed_stack_suggestions";
+const VOTES_COLLECTION: &str = "votes";
+const COMMENTS_COLLECTION: &str = "comments";
+const ANNOTATIONS_COLLECTION: &str = "annotations";
+const ALBUMS_COLLECTION: &str = "albums";
+const ALBUM_MEMBERS_COLLECTION: &str = "album_members";
+const CULL_SESSIONS_COLLECTION: &str = "cull_sessions";
+const CULL_CHOICES_COLLECTION: &str = "cull_choices";
+const ACTIVITY_COLLECTION: &str = "activity";
+const CONFIG_HISTORY_COLLECTION: &str = "config_history";
+/// Collections that every peer subscribes to in full.
+const SUBSCRIBED_COLLECTIONS: &[&str] = &[
+    SMART_ALBUMS_COLLECTION,
+    PEOPLE_COLLECTION,
+    DISMISSED_STACK_SUGGESTIONS_COLLECTION,
+];
+/// Documents about a single photo (`photo_id`), which carry a copy of the photo's visibility.
+const PHOTO_SCOPED_COLLECTIONS: &[&str] = &[
+    VOTES_COLLECTION,
+    COMMENTS_COLLECTION,
+    ANNOTATIONS_COLLECTION,
+    CONFIG_HISTORY_COLLECTION,
+    FACE_REGIONS_COLLECTION,
+    FACE_EMBEDDINGS_COLLECTION,
+];
+/// Documents about several photos (`photo_ids`), visible only to peers that may see all of them.
+const MULTI_PHOTO_SCOPED_COLLECTIONS: &[&str] = &[CULL_SESSIONS_COLLECTION, CULL_CHOICES_COLLECTION];
+
+/// Albums and their members are only synced to peers allowed to see them. This keeps a selection
+/// off other devices until it is shared; it is not access control against a modified client.
+const ALBUM_VISIBILITY_FILTER: &str =
+    "coalesce(restricted, false) = false OR created_by = :peer OR array_contains(visible_to, :peer)";
+const ALBUM_MEMBER_VISIBILITY_FILTER: &str =
+    "coalesce(restricted, false) = false OR album_created_by = :peer OR array_contains(visible_to, :peer)";
+/// A photo whose albums are all restricted is only synced to their viewers and the photo's
+/// author, and so is everything in `PHOTO_SCOPED_COLLECTIONS` about it. See `PhotoVisibility`.
+/// The same filter applies to `MULTI_PHOTO_SCOPED_COLLECTIONS`, whose visibility is an intersection.
+const PHOTO_VISIBILITY_FILTER: &str =
+    "coalesce(restricted, false) = false OR array_contains(visible_to, :peer)";
+/// Activity about a restricted album carries the album's viewers, so the log doesn't reveal it.
+const ACTIVITY_VISIBILITY_FILTER: &str =
+    "coalesce(restricted, false) = false OR actor_peer_key = :peer OR array_contains(visible_to, :peer)";
+const SET_LIBRARY_EVENT: &str = "SetLibrary";
+const SMART_ALBUMS_EVENT: &str = "SmartAlbums";
+const PRESENCE_EVENT: &str = "Presence";
+const NEW_COMMENTS_EVENT: &str = "NewComments";
+const ALBUMS_EVENT: &str = "Albums";
+const ACTIVITY_EVENT: &str = "Activity";
+const FULL_RES_ATTACHMENT_MAX_BYTES: u64 = 2 * 1024 * 1024;
+/// Near-duplicate groups are small; this bounds the ANN lookup behind `find_similar_photos`.
+const SIMILAR_PHOTOS_CANDIDATES: usize = 256;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub datetime: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PhotoConfig(pub String);
+
+pub const MAX_RATING: u8 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl From<String> for PhotoConfig {
+    fn from(s: String) -> Self {
+        PhotoConfig(s)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Photo {
+    #[serde(default)]
+    pub id: String,
+    pub image_path: String,
+    pub filename: String,
+    pub base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_res_attachment: Option<AttachmentTokenPayload>,
+    pub config: Option<PhotoConfig>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub stack_id: Option<String>,
+    #[serde(default)]
+    pub is_stack_primary: bool,
+    #[serde(default)]
+    pub metadata: Option<ImageMetadata>,
+    #[serde(default)]
+    pub perceptual_hash: Option<PerceptualHash>,
+    #[serde(default)]
+    pub quality: Option<QualityScore>,
+    #[serde(default)]
+    pub palette: Vec<PaletteColor>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AppAction {
+    SetImageLibraryContent {
+        images: Vec<Photo>
+    },
+    ClearImageLibraryContent,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AppState {
+    #[serde(default)]
+    pub images: Vec<Photo>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct StoredState {
+    _id: String,
+    state: AppState,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotoDocument {
+    _id: String,
+    filename: String,
+    image_path: String,
+    base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full_res_attachment: Option<DittoAttachmentToken>,
+    #[serde(default)]
+    author_peer_id: Option<String>,
+    pub config: Option<PhotoConfig>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub color_labels: Vec<ColorLabel>,
+    #[serde(default)]
+    pub rejected: bool,
+    #[serde(default)]
+    pub stack_id: Option<String>,
+    #[serde(default)]
+    pub is_stack_primary: bool,
+    #[serde(default)]
+    pub metadata: Option<ImageMetadata>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub perceptual_hash: Option<PerceptualHash>,
+    #[serde(default)]
+    pub quality: Option<QualityScore>,
+    #[serde(default)]
+    pub palette: Vec<PaletteColor>,
+    #[serde(default)]
+    pub machine_tags: Option<Vec<MachineTag>>,
+    #[serde(default)]
+    pub config_version_id: Option<String>,
+    #[serde(default)]
+    pub source_photo_id: Option<String>,
+    #[serde(default)]
+    pub copy_name: Option<String>,
+    /// Album ID -> what that album contributes to the photo's visibility; `null` once the photo
+    /// left the album. Kept on the photo so that every peer that can see the photo derives the
+    /// same `visibility`, including from albums it can't see itself.
+    #[serde(default)]
+    album_visibility: std::collections::HashMap<String, Option<PhotoVisibility>>,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+#[derive(Debug, Serialize)]
+struct PhotoDocumentWrite {
+    _id: String,
+    filename: String,
+    image_path: String,
+    base64: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full_res_attachment: Option<DittoAttachment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_peer_id: Option<String>,
+    pub config: Option<PhotoConfig>,
+    pub favorite: bool,
+    pub stack_id: Option<String>,
+    pub is_stack_primary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ImageMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub perceptual_hash: Option<PerceptualHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<QualityScore>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub palette: Vec<PaletteColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_photo_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SyncScopesArgs {
+    #[serde(rename = "syncScopes")]
+    sync_scopes: SyncScopes,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SyncScopes {
+    #[serde(rename = "photos")]
+    photos: String,
+    #[serde(rename = "app_state")]
+    app_state: String,
+    #[serde(rename = "smart_albums")]
+    smart_albums: String,
+    #[serde(rename = "people")]
+    people: String,
+    #[serde(rename = "face_regions")]
+    face_regions: String,
+    #[serde(rename = "face_embeddings")]
+    face_embeddings: String,
+    #[serde(rename = "dismissed_stack_suggestions")]
+    dismissed_stack_suggestions: String,
+    #[serde(rename = "votes")]
+    votes: String,
+    #[serde(rename = "comments")]
+    comments: String,
+    #[serde(rename = "annotations")]
+    annotations: String,
+    #[serde(rename = "albums")]
+    albums: String,
+    #[serde(rename = "album_members")]
+    album_members: String,
+    #[serde(rename = "cull_sessions")]
+    cull_sessions: String,
+    #[serde(rename = "cull_choices")]
+    cull_choices: String,
+    #[serde(rename = "activity")]
+    activity: String,
+    #[serde(rename = "config_history")]
+    config_history: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PhotoPayload {
+    pub id: String,
+    pub filename: String,
+    pub image_path: String,
+    pub base64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_res_attachment: Option<AttachmentTokenPayload>,
+    pub author_peer_id: Option<String>,
+    pub config: Option<PhotoConfig>,
+    pub favorite: bool,
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub color_labels: Vec<ColorLabel>,
+    #[serde(default)]
+    pub rejected: bool,
+    #[serde(default)]
+    pub stack_id: Option<String>,
+    pub is_stack_primary: bool,
+    #[serde(default)]
+    pub metadata: Option<ImageMetadata>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub perceptual_hash: Option<PerceptualHash>,
+    #[serde(default)]
+    pub quality: Option<QualityScore>,
+    #[serde(default)]
+    pub palette: Vec<PaletteColor>,
+    /// `None` until the classifier has looked at the photo.
+    #[serde(default)]
+    pub machine_tags: Option<Vec<MachineTag>>,
+    /// The config history entry `config` currently corresponds to; `None` before the first edit.
+    #[serde(default)]
+    pub config_version_id: Option<String>,
+    /// Set on virtual copies: the photo whose original and attachment this one shares.
+    #[serde(default)]
+    pub source_photo_id: Option<String>,
+    #[serde(default)]
+    pub copy_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SetLibraryPayload {
+    photos: Vec<PhotoPayload>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SimilarPhoto {
+    pub id: String,
+    pub distance: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ColorMatch {
+    pub id: String,
+    /// CIE76 delta E; below ~10 reads as the same color, above ~25 as a different one.
+    pub distance: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RankedPhoto {
+    pub id: String,
+    pub quality: Option<QualityScore>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttachmentTokenPayload {
+    pub id: String,
+    pub len: u64,
+    pub metadata: std::collections::HashMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PresencePeerPayload {
+    peer_key: String,
+    device_name: String,
+    metadata: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct PresencePayload {
+    local_peer: PresencePeerPayload,
+    remote_peers: Vec<PresencePeerPayload>,
+}
+
+pub struct DittoRepository {
+    state: Arc<RwLock<AppState>>,
+    ditto: Arc<Ditto>,
+    upsert_tx: mpsc::UnboundedSender<Vec<Photo>>,
+    face_index_tx: mpsc::UnboundedSender<()>,
+    semantic_index_tx: mpsc::UnboundedSender<()>,
+    similarity_index_tx: mpsc::UnboundedSender<()>,
+    quality_index_tx: mpsc::UnboundedSender<()>,
+    _observer: Arc<StoreObserver>,
+    _photos_observer: Arc<StoreObserver>,
+    _smart_albums_observer: Arc<StoreObserver>,
+    _comments_observer: Arc<StoreObserver>,
+    _activity_observer: Arc<StoreObserver>,
+    _albums_observers: Vec<Arc<StoreObserver>>,
+    _presence_observer: PresenceObserver,
+}
+
+impl DittoRepository {
+    pub async fn init(app: &AppHandle) -> Result<Self, String> {
+        load_dotenv(app)?;
+
+        let (app_id, playground_token, auth_url, websocket_url) = read_ditto_env()?;
+        let data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data dir: {e}"))?;
+        crate::analysis_cache::init(app)?;
+        crate::similarity_index::init(app)?;
+        let ditto_root = PersistentRoot::new(data_dir.join("ditto"))
+            .map_err(|e| format!("Failed to create Ditto data dir: {e}"))?;
+
+        let ditto = Ditto::builder()
+            .with_root(Arc::new(ditto_root))
+            .with_identity(|root| {
+                identity::OnlinePlayground::new(
+                    root,
+                    AppId::from_str(&app_id)?,
+                    playground_token.clone(),
+                    true,
+                    Some(&auth_url),
+                )
+            })
+            .map_err(|e| format!("Failed to configure Ditto identity: {e}"))?
+            .build()
+            .map_err(|e| format!("Failed to build Ditto instance: {e}"))?;
+        let ditto = Arc::new(ditto);
+
+        ditto
+            .disable_sync_with_v3()
+            .map_err(|e| format!("Failed to disable v3 sync: {e}"))?;
+
+        let device_name = ditto.presence().graph().local_peer.device_name.clone();
+        let user_name = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or(device_name);
+        let peer_metadata = serde_json::json!({ "name": user_name });
+        ditto
+            .presence()
+            .set_peer_metadata(&peer_metadata)
+            .map_err(|e| format!("Failed to set peer metadata: {e}"))?;
+        // in ditto 4.13 also write the DEVICE_NAME back to system (ALTER SYSTEM SET DEVICE_NAME = 'YourCustomDeviceName')
+
+        ditto
+            .presence()
+            .set_connection_request_handler(|connection_request: ConnectionRequest| {
+                let connection_type = connection_request.connection_type();
+                let peer_key = connection_request.peer_key_string();
+                let peer_metadata = connection_request.peer_metadata_json_str();
+                let identity_metadata = connection_request.identity_service_metadata_json_str();
+
+                println!(
+                    "Connection request: type={connection_type:?}, peer_key={peer_key}, peer_metadata={peer_metadata}, identity_metadata={identity_metadata}"
+                );
+
+                ConnectionRequestAuthorization::Allow
+                // if (true) {
+                // } else {
+                //     ConnectionRequestAuthorization::Deny
+                // }
+            });
+
+        ditto.update_transport_config(|transport_config| {
+            transport_config.enable_all_peer_to_peer();
+            transport_config.global.sync_group = 1; // all users in 1 big pool!
+            transport_config.connect.websocket_urls.clear();
+            transport_config.connect.websocket_urls.insert(websocket_url);
+            //BluetoothLe
+            transport_config.peer_to_peer.bluetooth_le.enabled = true;
+            //Local Area Network
+            transport_config.peer_to_peer.lan.enabled = true;
+            // Apple Wireless Direct Link
+            // transport_config.peer_to_peer.awdl.enabled = false;
+            //wifi aware
+            // transport_config.peer_to_peer.wifi_aware.enabled = false;
+            println!("Transport config: {:#?}", transport_config);
+        });
+
+        let sync_scopes = SyncScopesArgs {
+            sync_scopes: SyncScopes {
+                photos: "SmallPeersOnly".to_string(),
+                app_state: "SmallPeersOnly".to_string(),
+                smart_albums: "SmallPeersOnly".to_string(),
+                people: "SmallPeersOnly".to_string(),
+                face_regions: "SmallPeersOnly".to_string(),
+                face_embeddings: "SmallPeersOnly".to_string(),
+                dismissed_stack_suggestions: "SmallPeersOnly".to_string(),
+                votes: "SmallPeersOnly".to_string(),
+                comments: "SmallPeersOnly".to_string(),
+                annotations: "SmallPeersOnly".to_string(),
+                albums: "SmallPeersOnly".to_string(),
+                album_members: "SmallPeersOnly".to_string(),
+                cull_sessions: "SmallPeersOnly".to_string(),
+                cull_choices: "SmallPeersOnly".to_string(),
+                activity: "SmallPeersOnly".to_string(),
+                config_history: "SmallPeersOnly".to_string(),
+            },
+        };
+        ditto
+            .store()
+            .execute_v2((
+                "ALTER SYSTEM SET USER_COLLECTION_SYNC_SCOPES = :syncScopes",
+                sync_scopes,
+            ))
+            .await
+            .map_err(|e| format!("Failed to set Ditto sync scopes: {e}"))?;
+
+        ditto
+            .start_sync()
+            .map_err(|e| format!("Failed to start Ditto sync: {e}"))?;
+    
+        for collection in SUBSCRIBED_COLLECTIONS {
+            ditto
+                .sync()
+                .register_subscription_v2(format!("SELECT * FROM {collection}"))
+                .map_err(|e| format!("Failed to register {collection} subscription: {e}"))?;
+        }
+        let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+        for (collection, filter) in filtered_collections() {
+            ditto
+                .sync()
+                .register_subscription_v2((
+                    format!("SELECT * FROM {collection} WHERE {filter}"),
+                    serde_json::json!({ "peer": local_peer_key }),
+                ))
+                .map_err(|e| format!("Failed to register {collection} subscription: {e}"))?;
+        }
+
+        let initial_state = load_state(ditto.as_ref()).await?;
+        let state = Arc::new(RwLock::new(initial_state));
+        let (upsert_tx, mut upsert_rx) = mpsc::unbounded_channel::<Vec<Photo>>();
+        let ditto_for_worker = ditto.clone();
+        let app_handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(images) = upsert_rx.recv().await {
+                if let Err(error) = upsert_photos_from_paths_with_ditto(ditto_for_worker.as_ref(), &images).await {
+                    eprintln!("{error}");
+                    continue;
+                }
+                let ids = images.iter().map(|image| image.id.clone()).collect();
+                if let Err(error) =
+                    append_activity(
+                        ditto_for_worker.as_ref(),
+                        ActivityAction::ImportPhotos,
+                        ids,
+                        None,
+                        PhotoVisibility::default(),
+                    )
+                    .await
+                {
+                    eprintln!("{error}");
+                }
+                if let Err(error) = emit_library_snapshot(ditto_for_worker.as_ref(), &app_handle).await {
+                    eprintln!("{error}");
+                }
+            }
+        });
+
+        let observer = install_state_observer(ditto.as_ref(), state.clone())?;
+        let face_index_tx = crate::face_indexer::spawn(app.clone());
+        let semantic_index_tx = crate::semantic_indexer::spawn(app.clone());
+        let similarity_index_tx = crate::similarity_index::spawn(app.clone());
+        let quality_index_tx = crate::quality_indexer::spawn(app.clone());
+        let photos_observer = install_photos_observer(
+            ditto.clone(),
+            app,
+            vec![
+                face_index_tx.clone(),
+                semantic_index_tx.clone(),
+                similarity_index_tx.clone(),
+                quality_index_tx.clone(),
+            ],
+        )?;
+        let smart_albums_observer = install_smart_albums_observer(ditto.clone(), app)?;
+        let comments_observer = install_comments_observer(ditto.clone(), app)?;
+        let activity_observer = install_activity_observer(ditto.clone(), app)?;
+        let albums_observers = install_albums_observers(ditto.clone(), app)?;
+        let presence_observer = install_presence_observer(ditto.clone(), app)?;
+        emit_library_snapshot(ditto.as_ref(), app).await?;
+        emit_smart_albums_snapshot(ditto.as_ref(), app).await?;
+        emit_albums_snapshot(ditto.as_ref(), app).await?;
+        emit_presence_snapshot(ditto.as_ref(), app)?;
+
+        Ok(Self {
+            state,
+            ditto,
+            upsert_tx,
+            face_index_tx,
+            semantic_index_tx,
+            similarity_index_tx,
+            quality_index_tx,
+            _observer: observer,
+            _photos_observer: photos_observer,
+            _smart_albums_observer: smart_albums_observer,
+            _comments_observer: comments_observer,
+            _activity_observer: activity_observer,
+            _albums_observers: albums_observers,
+            _presence_observer: presence_observer,
+        })
+    }
+
+    pub fn get_state(&self) -> AppState {
+        self.state
+            .read()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    pub async fn dispatch(&self, action: AppAction) -> Result<AppState, String> {
+        let updated = {
+            let mut state = self
+                .state
+                .write()
+                .map_err(|_| "Failed to lock app state".to_string())?;
+            state.reduce(action);
+            state.clone()
+        };
+
+        persist_state(&self.ditto, &updated).await?;
+        Ok(updated)
+    }
+
+    pub async fn upsert_photos_from_paths(&self, images: &[Photo]) -> Result<(), String> {
+        upsert_photos_from_paths_with_ditto(self.ditto.as_ref(), images).await?;
+        let ids = images.iter().map(|image| image.id.clone()).collect();
+        self.log_activity(ActivityAction::ImportPhotos, ids, None).await;
+        Ok(())
+    }
+
+    pub async fn enqueue_upsert_photos_from_paths(&self, images: Vec<Photo>) -> Result<(), String> {
+        self.upsert_tx
+            .send(images)
+            .map_err(|_| "Failed to queue photo upsert".to_string())
+    }
+
+    pub async fn get_photos(&self) -> Result<Vec<PhotoPayload>, String> {
+        query_photos(self.ditto.as_ref()).await
+    }
+
+    /// Refuses to remove a photo that virtual copies still point at, since they would lose
+    /// their original; the copies have to be removed first.
+    pub async fn remove_photo(&self, id: &str) -> Result<(), String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {PHOTOS_COLLECTION} WHERE source_photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query virtual copies: {e}"))?;
+        if result.iter().next().is_some() {
+            return Err(format!("Photo {id} still has virtual copies; remove them first"));
+        }
+        self.remove_photo_documents(id).await?;
+        self.log_activity(ActivityAction::RemovePhoto, vec![id.to_string()], None)
+            .await;
+        Ok(())
+    }
+
+    async fn remove_photo_documents(&self, id: &str) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("DELETE FROM {PHOTOS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove Ditto photo: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {VOTES_COLLECTION} WHERE photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove photo votes: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {COMMENTS_COLLECTION} WHERE photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove photo comments: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {ANNOTATIONS_COLLECTION} WHERE photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove photo annotations: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {ALBUM_MEMBERS_COLLECTION} WHERE photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove photo from albums: {e}"))?;
+        store
+            .execute_v2((
+                format!("DELETE FROM {CONFIG_HISTORY_COLLECTION} WHERE photo_id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to remove photo config history: {e}"))?;
+        for region in self.get_face_regions(Some(id)).await? {
+            self.delete_face_region(&region).await?;
+        }
+        Ok(())
+    }
+
+    /// A new library item on top of the same original and attachment as `source_id`, starting
+    /// from its current config. Favorite, ratings, labels, votes and history are its own.
+    pub async fn create_virtual_copy(
+        &self,
+        source_id: &str,
+        copy_name: Option<String>,
+    ) -> Result<PhotoPayload, String> {
+        let source = self
+            .get_photo(source_id)
+            .await?
+            .ok_or_else(|| format!("Photo {source_id} not found"))?;
+        // Copies of copies hang off the same original.
+        let source_photo_id = source.source_photo_id.clone().unwrap_or_else(|| source.id.clone());
+        let copy_name = copy_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        let doc = PhotoDocumentWrite {
+            _id: uuid::Uuid::new_v4().to_string(),
+            filename: source.filename,
+            image_path: source.image_path,
+            base64: source.base64,
+            full_res_attachment: None,
+            author_peer_id: source.author_peer_id,
+            config: source.config,
+            favorite: false,
+            stack_id: None,
+            is_stack_primary: false,
+            metadata: source.metadata,
+            perceptual_hash: source.perceptual_hash,
+            quality: source.quality,
+            palette: source.palette,
+            source_photo_id: Some(source_photo_id.clone()),
+            copy_name,
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {PHOTOS_COLLECTION} DOCUMENTS (:doc)"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to create virtual copy: {e}"))?;
+        let scope = std::collections::HashSet::from([doc._id.clone()]);
+        sync_photo_visibility(self.ditto.as_ref(), Some(&scope)).await?;
+        if !source.tags.is_empty() {
+            self.update_photo_tags(&doc._id, &source.tags).await?;
+        }
+        self.log_activity(
+            ActivityAction::CreateVirtualCopy,
+            vec![source_photo_id, doc._id.clone()],
+            doc.copy_name.clone(),
+        )
+        .await;
+        self.get_photo(&doc._id)
+            .await?
+            .ok_or_else(|| format!("Virtual copy {} not found", doc._id))
+    }
+
+    pub async fn clear_library(&self) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2(format!(
+                "DELETE FROM {PHOTOS_COLLECTION} WHERE _id != ''"
+            ))
+            .await
+            .map_err(|e| format!("Failed to clear Ditto photos: {e}"))?;
+        for collection in [
+            FACE_REGIONS_COLLECTION,
+            FACE_EMBEDDINGS_COLLECTION,
+            VOTES_COLLECTION,
+            COMMENTS_COLLECTION,
+            ANNOTATIONS_COLLECTION,
+            ALBUM_MEMBERS_COLLECTION,
+            CULL_SESSIONS_COLLECTION,
+            CULL_CHOICES_COLLECTION,
+            CONFIG_HISTORY_COLLECTION,
+        ] {
+            store
+                .execute_v2(format!("DELETE FROM {collection} WHERE _id != ''"))
+                .await
+                .map_err(|e| format!("Failed to clear {collection}: {e}"))?;
+        }
+
+        self.dispatch(AppAction::ClearImageLibraryContent).await?;
+        self.log_activity(ActivityAction::ClearLibrary, Vec::new(), None)
+            .await;
+        Ok(())
+    }
+
+    pub async fn update_photo_favorite(
+        &self,
+        id: &str,
+        favorite: bool,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET favorite = :favorite WHERE _id = :id"),
+                serde_json::json!({ "favorite": favorite, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo favorite: {e}"))?;
+        self.log_activity(
+            ActivityAction::SetFavorite,
+            vec![id.to_string()],
+            Some(favorite.to_string()),
+        )
+        .await;
+        Ok(())
+    }
+
+    pub async fn update_photos_favorite(
+        &self,
+        ids: Vec<String>,
+        favorite: bool,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        for id in &ids {
+            store
+                .execute_v2((
+                    format!("UPDATE {PHOTOS_COLLECTION} SET favorite = :favorite WHERE _id = :id"),
+                    serde_json::json!({ "favorite": favorite, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo favorite: {e}"))?;
+        }
+        self.log_activity(ActivityAction::SetFavorite, ids, Some(favorite.to_string()))
+            .await;
+        Ok(())
+    }
+
+    pub async fn update_photo_rating(&self, id: &str, rating: u8) -> Result<(), String> {
+        self.update_photos_rating(vec![id.to_string()], rating).await
+    }
+
+    pub async fn update_photos_rating(&self, ids: Vec<String>, rating: u8) -> Result<(), String> {
+        if rating > MAX_RATING {
+            return Err(format!("Rating must be between 0 and {MAX_RATING}, got {rating}"));
+        }
+        let store = self.ditto.store();
+        for id in &ids {
+            store
+                .execute_v2((
+                    format!("UPDATE {PHOTOS_COLLECTION} SET rating = :rating WHERE _id = :id"),
+                    serde_json::json!({ "rating": rating, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo rating: {e}"))?;
+        }
+        self.log_activity(ActivityAction::SetRating, ids, Some(rating.to_string()))
+            .await;
+        Ok(())
+    }
+
+    pub async fn update_photo_color_labels(
+        &self,
+        id: &str,
+        color_labels: Vec<ColorLabel>,
+    ) -> Result<(), String> {
+        self.update_photos_color_labels(vec![id.to_string()], color_labels)
+            .await
+    }
+
+    /// Replaces the labels of every photo in `ids`; an empty list clears them.
+    pub async fn update_photos_color_labels(
+        &self,
+        ids: Vec<String>,
+        mut color_labels: Vec<ColorLabel>,
+    ) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        color_labels.retain(|label| seen.insert(*label));
+        let store = self.ditto.store();
+        for id in &ids {
+            store
+                .execute_v2((
+                    format!("UPDATE {PHOTOS_COLLECTION} SET color_labels = :color_labels WHERE _id = :id"),
+                    serde_json::json!({ "color_labels": color_labels, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo color labels: {e}"))?;
+        }
+        let detail = serde_json::to_string(&color_labels).ok();
+        self.log_activity(ActivityAction::SetColorLabels, ids, detail)
+            .await;
+        Ok(())
+    }
+
+    pub async fn update_photos_rejected(&self, ids: Vec<String>, rejected: bool) -> Result<(), String> {
+        let store = self.ditto.store();
+        for id in &ids {
+            store
+                .execute_v2((
+                    format!("UPDATE {PHOTOS_COLLECTION} SET rejected = :rejected WHERE _id = :id"),
+                    serde_json::json!({ "rejected": rejected, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo rejected flag: {e}"))?;
+        }
+        self.log_activity(ActivityAction::SetRejected, ids, Some(rejected.to_string()))
+            .await;
+        Ok(())
+    }
+
+    pub async fn update_photo_stack(
+        &self,
+        photo_ids: Vec<String>,
+        stack_id: String,
+        primary_id: String,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        for id in &photo_ids {
+            let is_primary = *id == primary_id;
+            store
+                .execute_v2((
+                    format!(
+                        "UPDATE {PHOTOS_COLLECTION} SET stack_id = :stack_id, is_stack_primary = :is_stack_primary WHERE _id = :id"
+                    ),
+                    serde_json::json!({ "stack_id": stack_id, "is_stack_primary": is_primary, "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo stack: {e}"))?;
+        }
+        self.log_activity(ActivityAction::SetStack, photo_ids, Some(stack_id))
+            .await;
+        Ok(())
+    }
+
+    pub async fn set_stack_primary(
+        &self,
+        stack_id: &str,
+        primary_id: &str,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!(
+                    "UPDATE {PHOTOS_COLLECTION} SET is_stack_primary = false WHERE stack_id = :stack_id"
+                ),
+                serde_json::json!({ "stack_id": stack_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to reset stack primary: {e}"))?;
+        store
+            .execute_v2((
+                format!(
+                    "UPDATE {PHOTOS_COLLECTION} SET is_stack_primary = true WHERE _id = :id"
+                ),
+                serde_json::json!({ "id": primary_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to set stack primary: {e}"))?;
+        self.log_activity(
+            ActivityAction::SetStackPrimary,
+            vec![primary_id.to_string()],
+            Some(stack_id.to_string()),
+        )
+        .await;
+        Ok(())
+    }
+
+    pub async fn clear_photo_stack(&self, photo_ids: Vec<String>) -> Result<(), String> {
+        let store = self.ditto.store();
+        for id in &photo_ids {
+            store
+                .execute_v2((
+                    format!(
+                        "UPDATE {PHOTOS_COLLECTION} SET stack_id = NULL, is_stack_primary = false WHERE _id = :id"
+                    ),
+                    serde_json::json!({ "id": id }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to clear photo stack: {e}"))?;
+        }
+        self.log_activity(ActivityAction::ClearStack, photo_ids, None)
+            .await;
+        Ok(())
+    }
+
+    pub fn local_peer_key(&self) -> String {
+        self.ditto
+            .presence()
+            .graph()
+            .local_peer
+            .peer_key_string
+            .clone()
+    }
+
+    /// The `name` this peer advertises in its presence metadata, falling back to the device name.
+    pub fn local_display_name(&self) -> String {
+        let local_peer = self.ditto.presence().graph().local_peer;
+        local_peer
+            .peer_metadata
+            .get("name")
+            .and_then(|name| name.as_str())
+            .map(str::to_string)
+            .unwrap_or(local_peer.device_name)
+    }
+
+    pub async fn get_photo(&self, id: &str) -> Result<Option<PhotoPayload>, String> {
+        let store = self.ditto.store();
+        let result = store
+            .execute_v2((
+                format!("SELECT * FROM {PHOTOS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query Ditto photo: {e}"))?;
+        Ok(collect_photo_payloads(&result).into_iter().next())
+    }
+
+    async fn update_photo_metadata(
+        &self,
+        id: &str,
+        metadata: &ImageMetadata,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET metadata = :metadata WHERE _id = :id"),
+                serde_json::json!({ "metadata": metadata, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo metadata: {e}"))?;
+        Ok(())
+    }
+
+    /// Returns the shifted photos and the IDs of those without a capture time to shift.
+    pub async fn shift_photos_capture_time(
+        &self,
+        ids: Vec<String>,
+        offset_seconds: i64,
+    ) -> Result<(Vec<PhotoPayload>, Vec<String>), String> {
+        let mut updated = Vec::new();
+        let mut skipped = Vec::new();
+        for id in ids {
+            let Some(mut photo) = self.get_photo(&id).await? else {
+                skipped.push(id);
+                continue;
+            };
+            let Some(metadata) = photo.metadata.as_mut() else {
+                skipped.push(id);
+                continue;
+            };
+            let Some(shifted) = metadata
+                .datetime
+                .as_deref()
+                .and_then(|datetime| crate::metadata::shift_capture_time(datetime, offset_seconds))
+            else {
+                skipped.push(id);
+                continue;
+            };
+            metadata.datetime = Some(shifted);
+            self.update_photo_metadata(&id, metadata).await?;
+            updated.push(photo);
+        }
+        self.log_activity(
+            ActivityAction::EditCaptureTime,
+            updated.iter().map(|photo| photo.id.clone()).collect(),
+            Some(format!("{offset_seconds:+}s")),
+        )
+        .await;
+        Ok((updated, skipped))
+    }
+
+    pub async fn sync_photos_capture_time(
+        &self,
+        ids: Vec<String>,
+        reference_id: &str,
+        reference_datetime: &str,
+    ) -> Result<(Vec<PhotoPayload>, Vec<String>), String> {
+        let reference = self
+            .get_photo(reference_id)
+            .await?
+            .ok_or_else(|| format!("Reference photo {reference_id} not found"))?;
+        let current = reference
+            .metadata
+            .and_then(|metadata| metadata.datetime)
+            .ok_or_else(|| format!("Reference photo {reference_id} has no capture time"))?;
+        let offset_seconds = crate::metadata::capture_time_offset_seconds(&current, reference_datetime)
+            .ok_or_else(|| format!("Invalid reference capture time: {reference_datetime}"))?;
+        self.shift_photos_capture_time(ids, offset_seconds).await
+    }
+
+    pub async fn update_photos_location(
+        &self,
+        ids: Vec<String>,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<PhotoPayload>, String> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(format!("Invalid coordinates: {latitude}, {longitude}"));
+        }
+        let mut updated = Vec::new();
+        for id in ids {
+            let Some(mut photo) = self.get_photo(&id).await? else {
+                continue;
+            };
+            let metadata = photo.metadata.get_or_insert_with(ImageMetadata::default);
+            metadata.latitude = Some(latitude);
+            metadata.longitude = Some(longitude);
+            self.update_photo_metadata(&id, metadata).await?;
+            updated.push(photo);
+        }
+        self.log_activity(
+            ActivityAction::EditLocation,
+            updated.iter().map(|photo| photo.id.clone()).collect(),
+            Some(format!("{latitude}, {longitude}")),
+        )
+        .await;
+        Ok(updated)
+    }
+
+    async fn update_photo_tags(&self, id: &str, tags: &[String]) -> Result<(), String> {
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET tags = :tags WHERE _id = :id"),
+                serde_json::json!({ "tags": tags, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo tags: {e}"))?;
+        Ok(())
+    }
+
+    /// Applies `edit` to the tags of each photo and stores the ones that changed.
+    async fn edit_photos_tags(
+        &self,
+        photos: Vec<PhotoPayload>,
+        edit: impl Fn(&[String]) -> Vec<String>,
+    ) -> Result<Vec<PhotoPayload>, String> {
+        let mut updated = Vec::new();
+        for mut photo in photos {
+            let tags = crate::tags::dedup_tags(edit(&photo.tags));
+            if tags == crate::tags::dedup_tags(photo.tags.clone()) {
+                continue;
+            }
+            self.update_photo_tags(&photo.id, &tags).await?;
+            photo.tags = tags;
+            updated.push(photo);
+        }
+        Ok(updated)
+    }
+
+    async fn get_photos_by_id(&self, ids: &[String]) -> Result<Vec<PhotoPayload>, String> {
+        let mut photos = Vec::new();
+        for id in ids {
+            if let Some(photo) = self.get_photo(id).await? {
+                photos.push(photo);
+            }
+        }
+        Ok(photos)
+    }
+
+    pub async fn add_photos_tags(
+        &self,
+        ids: Vec<String>,
+        tags: Vec<String>,
+    ) -> Result<Vec<PhotoPayload>, String> {
+        let tags: Vec<String> = tags.iter().filter_map(|tag| normalize_tag(tag)).collect();
+        if tags.is_empty() {
+            return Err("No valid tags given".to_string());
+        }
+        let photos = self.get_photos_by_id(&ids).await?;
+        let updated = self
+            .edit_photos_tags(photos, |current| {
+                current.iter().chain(&tags).cloned().collect()
+            })
+            .await?;
+        self.log_tag_activity(ActivityAction::AddTags, &updated, tags.join(", "))
+            .await;
+        Ok(updated)
+    }
+
+    /// Removing a tag also removes everything below it.
+    pub async fn remove_photos_tags(
+        &self,
+        ids: Vec<String>,
+        tags: Vec<String>,
+    ) -> Result<Vec<PhotoPayload>, String> {
+        let tags: Vec<String> = tags.iter().filter_map(|tag| normalize_tag(tag)).collect();
+        let photos = self.get_photos_by_id(&ids).await?;
+        let updated = self
+            .edit_photos_tags(photos, |current| {
+                current
+                    .iter()
+                    .filter(|tag| !tags.iter().any(|removed| crate::tags::is_within(tag, removed)))
+                    .cloned()
+                    .collect()
+            })
+            .await?;
+        self.log_tag_activity(ActivityAction::RemoveTags, &updated, tags.join(", "))
+            .await;
+        Ok(updated)
+    }
+
+    /// Renames `from` and its subtree across the library. Renaming onto an existing tag merges them.
+    pub async fn rename_tag(&self, from: &str, to: &str) -> Result<Vec<PhotoPayload>, String> {
+        let from = normalize_tag(from).ok_or_else(|| format!("Invalid tag: {from}"))?;
+        let to = normalize_tag(to).ok_or_else(|| format!("Invalid tag: {to}"))?;
+        if crate::tags::is_within(&to, &from) && to != from {
+            return Err(format!("Cannot move {from} below itself"));
+        }
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        let updated = self
+            .edit_photos_tags(photos, |current| {
+                current
+                    .iter()
+                    .map(|tag| crate::tags::rename_tag(tag, &from, &to).unwrap_or_else(|| tag.clone()))
+                    .collect()
+            })
+            .await?;
+        self.log_tag_activity(ActivityAction::RenameTag, &updated, format!("{from} -> {to}"))
+            .await;
+        Ok(updated)
+    }
+
+    async fn log_tag_activity(&self, action: ActivityAction, updated: &[PhotoPayload], detail: String) {
+        if updated.is_empty() {
+            return;
+        }
+        let targets = updated.iter().map(|photo| photo.id.clone()).collect();
+        self.log_activity(action, targets, Some(detail)).await;
+    }
+
+    pub async fn merge_tags(
+        &self,
+        sources: Vec<String>,
+        target: &str,
+    ) -> Result<Vec<PhotoPayload>, String> {
+        let mut updated: Vec<PhotoPayload> = Vec::new();
+        for source in sources {
+            for photo in self.rename_tag(&source, target).await? {
+                updated.retain(|existing| existing.id != photo.id);
+                updated.push(photo);
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Every tag node in the library with its photo count, in path order so trees render directly.
+    pub async fn get_tag_counts(&self) -> Result<Vec<TagCount>, String> {
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        Ok(crate::tags::count_tags(photos.iter().map(|photo| photo.tags.as_slice()))
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect())
+    }
+
+    pub async fn update_photo_machine_tags(
+        &self,
+        id: &str,
+        tags: &[MachineTag],
+    ) -> Result<(), String> {
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET machine_tags = :machine_tags WHERE _id = :id"),
+                serde_json::json!({ "machine_tags": tags, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo machine tags: {e}"))?;
+        Ok(())
+    }
+
+    /// Every machine tag in the library with the number of photos carrying it, most common first.
+    pub async fn get_machine_tag_counts(&self, min_confidence: f32) -> Result<Vec<TagCount>, String> {
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+        for tag in photos
+            .iter()
+            .flat_map(|photo| photo.machine_tags.iter().flatten())
+            .filter(|tag| tag.confidence >= min_confidence)
+        {
+            *counts.entry(tag.tag.clone()).or_default() += 1;
+        }
+        let mut counts: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.tag.cmp(&b.tag)));
+        Ok(counts)
+    }
+
+    /// Photos whose machine tags match `query` (case-insensitive prefix), most confident first.
+    pub async fn search_machine_tags(
+        &self,
+        query: &str,
+        min_confidence: f32,
+    ) -> Result<Vec<String>, String> {
+        let query = query.trim().to_lowercase();
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        let mut matches: Vec<(String, f32)> = photos
+            .iter()
+            .filter_map(|photo| {
+                let confidence = photo
+                    .machine_tags
+                    .iter()
+                    .flatten()
+                    .filter(|tag| tag.confidence >= min_confidence && tag.tag.starts_with(&query))
+                    .map(|tag| tag.confidence)
+                    .fold(None, |best: Option<f32>, c| Some(best.map_or(c, |b| b.max(c))))?;
+                Some((photo.id.clone(), confidence))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        Ok(matches.into_iter().map(|(id, _)| id).collect())
+    }
+
+    /// Photos with a dominant color within `max_distance` of `hex`, closest first.
+    pub async fn search_photos_by_color(
+        &self,
+        hex: &str,
+        max_distance: f32,
+    ) -> Result<Vec<ColorMatch>, String> {
+        if crate::color_palette::parse_hex(hex).is_none() {
+            return Err(format!("Invalid color: {hex}"));
+        }
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        let mut matches: Vec<ColorMatch> = photos
+            .iter()
+            .filter_map(|photo| {
+                let distance = color_distance(&photo_palette(photo), hex)?;
+                (distance <= max_distance).then(|| ColorMatch {
+                    id: photo.id.clone(),
+                    distance,
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| a.distance.total_cmp(&b.distance).then(a.id.cmp(&b.id)));
+        Ok(matches)
+    }
+
+    /// Re-scores photos whose originals live on this device; returns the IDs that were updated.
+    pub async fn score_photos(&self, ids: Vec<String>) -> Result<Vec<String>, String> {
+        let mut scored = Vec::new();
+        for id in ids {
+            let Some(photo) = self.get_photo(&id).await? else {
+                continue;
+            };
+            if !std::path::Path::new(&photo.image_path).exists() {
+                continue;
+            }
+            let path = photo.image_path.clone();
+            let quality = match tauri::async_runtime::spawn_blocking(move || crate::quality::score_file(&path))
+                .await
+                .map_err(|e| format!("Quality scoring task failed: {e}"))?
+            {
+                Ok(quality) => quality,
+                Err(error) => {
+                    eprintln!("Quality scoring: {error}");
+                    continue;
+                }
+            };
+            self.store_photo_quality(&id, &quality).await?;
+            scored.push(id);
+        }
+        if !scored.is_empty() {
+            self.log_activity(ActivityAction::ScoreQuality, scored.clone(), None)
+                .await;
+        }
+        Ok(scored)
+    }
+
+    /// Stack members best first, unscored ones last; with `apply_primary` the best one becomes
+    /// the stack primary.
+    /// Scores are derived data, so storing one isn't logged as activity.
+    pub async fn store_photo_quality(&self, id: &str, quality: &QualityScore) -> Result<(), String> {
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET quality = :quality WHERE _id = :id"),
+                serde_json::json!({ "quality": quality, "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo quality: {e}"))?;
+        Ok(())
+    }
+
+    pub async fn rank_stack(
+        &self,
+        stack_id: &str,
+        apply_primary: bool,
+    ) -> Result<Vec<RankedPhoto>, String> {
+        let result = self
+            .ditto
+            .store()
+            .execute_v2((
+                format!("SELECT * FROM {PHOTOS_COLLECTION} WHERE stack_id = :stack_id"),
+                serde_json::json!({ "stack_id": stack_id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query stack members: {e}"))?;
+        let mut ranked: Vec<RankedPhoto> = collect_photo_payloads(&result)
+            .iter()
+            .map(|photo| RankedPhoto {
+                id: photo.id.clone(),
+                quality: photo_quality(photo),
+            })
+            .collect();
+        if ranked.is_empty() {
+            return Err(format!("Stack {stack_id} not found"));
+        }
+        let overall = |photo: &RankedPhoto| photo.quality.as_ref().map_or(-1.0, |q| q.overall);
+        ranked.sort_by(|a, b| overall(b).total_cmp(&overall(a)).then(a.id.cmp(&b.id)));
+        if apply_primary {
+            self.set_stack_primary(stack_id, &ranked[0].id).await?;
+        }
+        Ok(ranked)
+    }
+
+    /// Near-duplicates of `id` within `threshold` bits of pHash distance, closest first.
+    /// Candidates come from the ANN index; before it is built every photo is compared.
+    pub async fn find_similar_photos(
+        &self,
+        id: &str,
+        threshold: u32,
+    ) -> Result<Vec<SimilarPhoto>, String> {
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        let target = photos
+            .iter()
+            .find(|photo| photo.id == id)
+            .ok_or_else(|| format!("Photo {id} not found"))?;
+        let target_hash = photo_hash(target).ok_or_else(|| format!("Photo {id} has no perceptual hash"))?;
+
+        let candidates: Vec<&PhotoPayload> =
+            match crate::similarity_index::nearest_by_phash(target, SIMILAR_PHOTOS_CANDIDATES) {
+                Some(nearest) => {
+                    let nearest: std::collections::HashSet<String> = nearest
+                        .into_iter()
+                        .filter(|(_, distance)| *distance <= threshold)
+                        .map(|(id, _)| id)
+                        .collect();
+                    photos.iter().filter(|photo| nearest.contains(&photo.id)).collect()
+                }
+                None => photos.iter().filter(|photo| photo.id != id).collect(),
+            };
+
+        let mut similar: Vec<(SimilarPhoto, u32)> = candidates
+            .into_iter()
+            .filter(|photo| photo.source_photo_id.is_none())
+            .filter_map(|photo| {
+                let (distance, tie_break) = target_hash.distance(&photo_hash(photo)?)?;
+                (distance <= threshold).then(|| {
+                    (
+                        SimilarPhoto {
+                            id: photo.id.clone(),
+                            distance,
+                        },
+                        tie_break,
+                    )
+                })
+            })
+            .collect();
+        similar.sort_by_key(|(photo, tie_break)| (photo.distance, *tie_break));
+        Ok(similar.into_iter().map(|(photo, _)| photo).collect())
+    }
+
+    pub fn request_face_indexing(&self) {
+        let _ = self.face_index_tx.send(());
+    }
+
+    pub fn request_semantic_indexing(&self) {
+        let _ = self.semantic_index_tx.send(());
+    }
+
+    pub fn request_similarity_indexing(&self) {
+        let _ = self.similarity_index_tx.send(());
+    }
+
+    pub fn request_quality_indexing(&self) {
+        let _ = self.quality_index_tx.send(());
+    }
+
+    pub async fn emit_library_snapshot(&self, app: &AppHandle) -> Result<(), String> {
+        emit_library_snapshot(self.ditto.as_ref(), app).await
+    }
+
+    pub async fn fetch_full_res_photo(&self, id: &str) -> Result<Option<String>, String> {
+        // Virtual copies carry no attachment of their own.
+        let id = match self.get_photo(id).await?.and_then(|photo| photo.source_photo_id) {
+            Some(source_id) => source_id,
+            None => id.to_string(),
+        };
+        let store = self.ditto.store();
+        let result = store
+            .execute_v2((
+                format!(
+                    "SELECT * FROM COLLECTION {PHOTOS_COLLECTION} (full_res_attachment ATTACHMENT) WHERE _id = :id"
+                ),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to query Ditto photo attachment: {e}"))?;
+
+        let Some(item) = result.iter().next() else {
+            return Ok(None);
+        };
+
+        let doc: PhotoDocument = item
+            .deserialize_value()
+            .map_err(|e| format!("Failed to deserialize Ditto photo: {e}"))?;
+        let Some(token) = doc.full_res_attachment else {
+            return Ok(None);
+        };
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let tx = std::sync::Arc::new(std::sync::Mutex::new(Some(tx)));
+        let _fetcher = store
+            .fetch_attachment(token.clone(), move |event| match event {
+                dittolive_ditto::store::attachment::DittoAttachmentFetchEvent::Completed {
+                    attachment,
+                } => {
+                    if let Some(sender) = tx.lock().ok().and_then(|mut s| s.take()) {
+                        let _ = sender.send(Ok(attachment));
+                    }
+                }
+                dittolive_ditto::store::attachment::DittoAttachmentFetchEvent::Deleted => {
+                    if let Some(sender) = tx.lock().ok().and_then(|mut s| s.take()) {
+                        let _ = sender.send(Err("Attachment deleted".to_string()));
+                    }
+                }
+                _ => {}
+            })
+            .map_err(|e| format!("Failed to fetch Ditto attachment: {e}"))?;
+
+        let attachment = tokio::time::timeout(std::time::Duration::from_secs(60), rx)
+            .await
+            .map_err(|_| "Timed out fetching attachment".to_string())?
+            .map_err(|_| "Attachment fetch cancelled".to_string())??;
+
+        let bytes = std::fs::read(attachment.path()).map_err(|e| e.to_string())?;
+        let mime_type = token
+            .metadata()
+            .get("mime_type")
+            .cloned()
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let encoded = general_purpose::STANDARD.encode(bytes);
+        Ok(Some(format!("data:{};base64,{}", mime_type, encoded)))
+    }
+}
+
+impl AppState {
+    fn reduce(&mut self, action: AppAction) {
+        match action {
+            AppAction::SetImageLibraryContent { images } => {
+                self.images = images;
+            }
+            AppAction::ClearImageLibraryContent => {
+                self.images.clear();
+            }
+        }
+    }
+}
+
+fn load_dotenv(app: &AppHandle) -> Result<(), String> {
+    if dotenvy::dotenv().is_ok() {
+        return Ok(());
+    }
+
+    let resource_env = app
+        .path()
+        .resolve(".env", BaseDirectory::Resource)
+        .map_err(|e| format!("Failed to resolve bundled .env: {e}"))?;
+
+    if resource_env.exists() {
+        dotenvy::from_path(resource_env)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to load bundled .env: {e}"))?;
+    }
+
+    Ok(())
+}
+
+fn read_ditto_env() -> Result<(String, String, String, String), String> {
+    let app_id = std::env::var("DITTO_APP_ID")
+        .or_else(|_| std::env::var("DITTO_DATABASE_ID"))
+        .map_err(|_| "Missing DITTO_APP_ID (or DITTO_DATABASE_ID)".to_string())?;
+    let playground_token = std::env::var("DITTO_PLAYGROUND_TOKEN")
+        .or_else(|_| std::env::var("DITTO_SHARED_TOKEN"))
+        .map_err(|_| "Missing DITTO_PLAYGROUND_TOKEN (or DITTO_SHARED_TOKEN)".to_string())?;
+    let auth_url = std::env::var("DITTO_AUTH_URL")
+        .map_err(|_| "Missing DITTO_AUTH_URL".to_string())?;
+    let websocket_url = std::env::var("DITTO_WEBSOCKET_URL")
+        .map_err(|_| "Missing DITTO_WEBSOCKET_URL".to_string())?;
+    Ok((app_id, playground_token, auth_url, websocket_url))
+}
+
+async fn load_state(ditto: &Ditto) -> Result<AppState, String> {
+    let store = ditto.store();
+    let query = (
+        format!("SELECT * FROM {STATE_COLLECTION} WHERE _id = :id"),
+        serde_json::json!({ "id": STATE_DOC_ID }),
+    );
+    let result = store
+        .execute_v2(query)
+        .await
+        .map_err(|e| format!("Failed to query Ditto state: {e}"))?;
+
+    let Some(item) = result.iter().next() else {
+        return Ok(AppState::default());
+    };
+
+    let stored: StoredState = match item.deserialize_value() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to deserialize Ditto state (schema mismatch?): {e}. Resetting state.");
+            return Ok(AppState::default());
+        }
+    };
+    Ok(normalize_state(stored.state))
+}
+
+async fn persist_state(ditto: &Ditto, state: &AppState) -> Result<(), String> {
+    let store = ditto.store();
+    let doc = StoredState {
+        _id: STATE_DOC_ID.to_string(),
+        state: state.clone(),
+    };
+
+    store
+        .execute_v2((
+            format!("INSERT INTO {STATE_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+            serde_json::json!({ "doc": doc }),
+        ))
+        .await
+        .map_err(|e| format!("Failed to persist Ditto state: {e}"))?;
+
+    Ok(())
+}
+
+fn install_state_observer(
+    ditto: &Ditto,
+    state: Arc<RwLock<AppState>>,
+) -> Result<Arc<StoreObserver>, String> {
+    let store = ditto.store();
+    let query = (
+        format!("SELECT * FROM {STATE_COLLECTION} WHERE _id = :id"),
+        serde_json::json!({ "id": STATE_DOC_ID }),
+    );
+    store
+        .register_observer_v2(query, move |query_result| {
+            let Some(item) = query_result.iter().next() else {
+                return;
+            };
+            let stored: Result<StoredState, _> = item.deserialize_value();
+            if let Ok(stored) = stored {
+                if let Ok(mut guard) = state.write() {
+                    *guard = normalize_state(stored.state);
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to register Ditto observer: {e}"))
+}
+
+fn normalize_state(mut state: AppState) -> AppState {
+    for photo in &mut state.images {
+        if photo.id.is_empty() {
+            photo.id = photo.image_path.clone();
+        }
+    }
+    state
+}
+
+/// Just the ID, for observers that only need to know which documents they saw.
+#[derive(Deserialize)]
+struct DocumentId {
+    _id: String,
+}
+
+fn install_photos_observer(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+    index_triggers: Vec<mpsc::UnboundedSender<()>>,
+) -> Result<Arc<StoreObserver>, String> {
+    let store = ditto.store();
+    let app_handle = app.clone();
+    let ditto_for_task = ditto.clone();
+    let (tx, mut rx) = mpsc::unbounded_channel::<std::collections::HashSet<String>>();
+    tauri::async_runtime::spawn(async move {
+        let mut known = std::collections::HashSet::new();
+        while let Some(mut photo_ids) = rx.recv().await {
+            while let Ok(newer) = rx.try_recv() {
+                photo_ids = newer;
+            }
+            // Photos synced in from other peers may carry album visibility this peer has yet to
+            // derive from. Only new photos need it: album changes re-derive the rest, and this
+            // pass's own writes don't show up as new photos.
+            let new_ids: std::collections::HashSet<String> =
+                photo_ids.difference(&known).cloned().collect();
+            known = photo_ids;
+            if !new_ids.is_empty() {
+                if let Err(error) = sync_photo_visibility(ditto_for_task.as_ref(), Some(&new_ids)).await {
+                    eprintln!("{error}");
+                }
+            }
+            if let Err(error) = evict_hidden_documents(ditto_for_task.as_ref()).await {
+                eprintln!("{error}");
+            }
+            if let Err(error) = emit_library_snapshot(ditto_for_task.as_ref(), &app_handle).await {
+                eprintln!("{error}");
+            }
+            if let Err(error) = emit_smart_albums_snapshot(ditto_for_task.as_ref(), &app_handle).await {
+                eprintln!("{error}");
+            }
+            for trigger in &index_triggers {
+                let _ = trigger.send(());
+            }
+        }
+    });
+    let query = format!("SELECT * FROM {PHOTOS_COLLECTION}");
+    store
+        .register_observer_v2(query, move |query_result| {
+            println!("<=== Emitting SetLibrary from observer");
+            let photo_ids = query_result
+                .iter()
+                .filter_map(|item| item.deserialize_value::<DocumentId>().ok())
+                .map(|doc| doc._id)
+                .collect();
+            let _ = tx.send(photo_ids);
+        })
+        .map_err(|e| format!("Failed to register photo observer: {e}"))
+}
+
+fn install_presence_observer(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+) -> Result<PresenceObserver, String> {
+    let app_handle = app.clone();
+    ditto
+        .presence()
+        .register_observer(move |graph| {
+            let payload = build_presence_payload(graph);
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(error) = app_handle.emit(PRESENCE_EVENT, payload) {
+                    eprintln!("{error}");
+                }
+            });
+        })
+        .map_err(|e| format!("Failed to register presence observer: {e}"))
+}
+
+async fn emit_library_snapshot(ditto: &Ditto, app: &AppHandle) -> Result<(), String> {
+    let photos = query_photos(ditto).await?;
+    println!("<=== Emitting SetLibrary: {:?}", photos.len());
+    let payload = SetLibraryPayload { photos };
+    app.emit(SET_LIBRARY_EVENT, payload)
+        .map_err(|e| format!("Failed to emit SetLibrary: {e}"))
+}
+
+/// Photos this peer may see; hidden ones can linger locally until they are evicted.
+async fn query_photos(ditto: &Ditto) -> Result<Vec<PhotoPayload>, String> {
+    let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+    let result = ditto
+        .store()
+        .execute_v2((
+            format!("SELECT * FROM {PHOTOS_COLLECTION} WHERE {PHOTO_VISIBILITY_FILTER}"),
+            serde_json::json!({ "peer": local_peer_key }),
+        ))
+        .await
+        .map_err(|e| format!("Failed to query Ditto photos: {e}"))?;
+    Ok(collect_photo_payloads(&result))
+}
+
+async fn upsert_photos_from_paths_with_ditto(
+    ditto: &Ditto,
+    images: &[Photo],
+) -> Result<(), String> {
+    let store = ditto.store();
+    let author_peer_id = ditto
+        .presence()
+        .graph()
+        .local_peer
+        .peer_key_string
+        .clone();
+    println!("Upsert: author_peer_id: {}", author_peer_id);
+    let mut seen = std::collections::HashSet::new();
+
+    let mut docs = Vec::new();
+
+    for image in images {
+        let filename = std::path::Path::new(&image.image_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| image.image_path.clone());
+
+        let doc_id = image.id.clone();
+
+        if !seen.insert(doc_id.clone()) {
+            continue;
+        }
+
+        let full_res_attachment =
+            match create_full_res_attachment(&store, &image.image_path).await {
+                Ok(attachment) => attachment,
+                Err(error) => {
+                    eprintln!(
+                        "Failed to create full-res attachment for {}: {}",
+                        image.image_path, error
+                    );
+                    None
+                }
+            };
+
+        let doc = PhotoDocumentWrite {
+            _id: doc_id.clone(),
+            filename: filename.clone(),
+            image_path: image.image_path.clone(),
+            base64: image.base64.clone(),
+            full_res_attachment,
+            author_peer_id: Some(author_peer_id.clone()),
+            config: image.config.clone(),
+            favorite: image.favorite,
+            stack_id: image.stack_id.clone(),
+            is_stack_primary: image.is_stack_primary,
+            metadata: image.metadata.clone(),
+            perceptual_hash: image.perceptual_hash.clone(),
+            quality: image.quality.clone(),
+            palette: image.palette.clone(),
+            source_photo_id: None,
+            copy_name: None,
+        };
+
+        docs.push(doc);
+    }
+
+    if docs.is_empty() {
+        return Ok(());
+    }
+
+    let docs_len = docs.len();
+    let (total_base64_bytes, max_base64_bytes, max_base64_path) = docs.iter().fold(
+        (0usize, 0usize, String::new()),
+        |(total, max, max_path), doc| {
+            let len = doc.base64.len();
+            if len > max {
+                (total + len, len, doc.image_path.clone())
+            } else {
+                (total + len, max, max_path)
+            }
+        },
+    );
+    println!(
+        "Upsert: docs: {}, total_base64_bytes: {}, max_base64_bytes: {} ({})",
+        docs_len,
+        total_base64_bytes,
+        max_base64_bytes,
+        max_base64_path
+    );
+
+    let start = Instant::now();
+    let timeout_after = std::time::Duration::from_secs(60);
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(5));
+    let store_for_task = store.clone();
+    let docs_for_task = docs;
+    let mut join = tauri::async_runtime::spawn_blocking(move || -> Result<(), String> {
+        tauri::async_runtime::block_on(async move {
+            const INSERT_BATCH_SIZE: usize = 50;
+            for chunk in docs_for_task.chunks(INSERT_BATCH_SIZE) {
+                let mut query_parts = Vec::with_capacity(chunk.len());
+                let mut payload = serde_json::Map::with_capacity(chunk.len());
+                for (idx, doc) in chunk.iter().enumerate() {
+                    let key = format!("doc{idx}");
+                    query_parts.push(format!("(:{key})"));
+                    payload.insert(key, serde_json::to_value(doc).map_err(|e| e.to_string())?);
+                }
+                let insert_query = format!(
+                    "INSERT INTO COLLECTION {PHOTOS_COLLECTION} (full_res_attachment ATTACHMENT) DOCUMENTS {} ON ID CONFLICT DO UPDATE",
+                    query_parts.join(", ")
+                );
+                store_for_task
+                    .execute_v2((insert_query, serde_json::Value::Object(payload)))
+                    .await
+                    .map_err(|e| format!("Failed to upsert Ditto photos: {e}"))?;
+            }
+            Ok(())
+        })
+    });
+
+    loop {
+        tokio::select! {
+            result = &mut join => {
+                let query_result = result.map_err(|e| format!("Upsert task failed: {e}"))?;
+                query_result.map_err(|e| format!("Failed to upsert Ditto photos: {e}"))?;
+                break;
+            }
+            _ = ticker.tick() => {
+                let elapsed = start.elapsed();
+                println!("Upsert: still running... {:?}", elapsed);
+                if elapsed >= timeout_after {
+                    return Err(format!(
+                        "Upsert timed out after {:?} ({} docs)",
+                        elapsed,
+                        docs_len
+                    ));
+                }
+            }
+        }
+    }
+
+    println!("Upsert: done in {:?}", start.elapsed());
+    Ok(())
+}
+
+fn emit_presence_snapshot(ditto: &Ditto, app: &AppHandle) -> Result<(), String> {
+    let graph = ditto.presence().graph();
+    let payload = build_presence_payload(&graph);
+    app.emit(PRESENCE_EVENT, payload)
+        .map_err(|e| format!("Failed to emit Presence: {e}"))
+}
+
+fn build_presence_payload(graph: &PresenceGraph) -> PresencePayload {
+    let local_peer = build_presence_peer_payload(&graph.local_peer);
+    let remote_peers = graph
+        .remote_peers
+        .iter()
+        .map(build_presence_peer_payload)
+        .collect();
+    PresencePayload {
+        local_peer,
+        remote_peers,
+    }
+}
+
+fn build_presence_peer_payload(peer: &Peer) -> PresencePeerPayload {
+    PresencePeerPayload {
+        peer_key: peer.peer_key_string.clone(),
+        device_name: peer.device_name.clone(),
+        metadata: peer.peer_metadata.clone(),
+    }
+}
+
+fn collect_photo_payloads(query_result: &QueryResult) -> Vec<PhotoPayload> {
+    let photos = query_result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<PhotoDocument>().ok())
+        .map(|doc| {
+            PhotoPayload {
+                id: doc._id,
+                filename: doc.filename,
+                image_path: doc.image_path,
+                base64: doc.base64,
+                full_res_attachment: doc
+                    .full_res_attachment
+                    .as_ref()
+                    .map(attachment_token_to_payload),
+                author_peer_id: doc.author_peer_id,
+                config: doc.config,
+                favorite: doc.favorite,
+                rating: doc.rating,
+                color_labels: doc.color_labels,
+                rejected: doc.rejected,
+                stack_id: doc.stack_id,
+                is_stack_primary: doc.is_stack_primary,
+                metadata: doc.metadata,
+                tags: doc.tags,
+                perceptual_hash: doc.perceptual_hash,
+                quality: doc.quality,
+                palette: doc.palette,
+                machine_tags: doc.machine_tags,
+                config_version_id: doc.config_version_id,
+                source_photo_id: doc.source_photo_id,
+                copy_name: doc.copy_name,
+            }
+        })
+        .collect();
+    group_virtual_copies(photos)
+}
+
+/// Places each virtual copy right after its source, so the library lists them together.
+fn group_virtual_copies(photos: Vec<PhotoPayload>) -> Vec<PhotoPayload> {
+    let sources: std::collections::HashSet<String> = photos
+        .iter()
+        .filter(|photo| photo.source_photo_id.is_none())
+        .map(|photo| photo.id.clone())
+        .collect();
+    let mut copies: std::collections::HashMap<String, Vec<PhotoPayload>> =
+        std::collections::HashMap::new();
+    let mut grouped = Vec::with_capacity(photos.len());
+    for photo in photos {
+        match photo.source_photo_id.clone() {
+            Some(source) if sources.contains(&source) => copies.entry(source).or_default().push(photo),
+            _ => grouped.push(photo),
+        }
+    }
+    let mut index = 0;
+    while index < grouped.len() {
+        let id = grouped[index].id.clone();
+        index += 1;
+        if let Some(mut group) = copies.remove(&id) {
+            group.sort_by(|a, b| a.copy_name.cmp(&b.copy_name).then(a.id.cmp(&b.id)));
+            let len = group.len();
+            grouped.splice(index..index, group);
+            index += len;
+        }
+    }
+    grouped
+}
+
+fn attachment_token_to_payload(token: &DittoAttachmentToken) -> AttachmentTokenPayload {
+    AttachmentTokenPayload {
+        id: token.id(),
+        len: token.len(),
+        metadata: token.metadata().clone(),
+    }
+}
+
+async fn create_full_res_attachment(
+    store: &dittolive_ditto::store::Store,
+    image_path: &str,
+) -> Result<Option<DittoAttachment>, String> {
+    println!("Creating full res attachment for {}", image_path);
+    let metadata = std::fs::metadata(image_path).map_err(|e| e.to_string())?;
+
+    let mut user_data = std::collections::HashMap::new();
+    if let Some(name) = std::path::Path::new(image_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+    {
+        user_data.insert("name".to_string(), name);
+    }
+
+    if let Some(mime_type) = guess_mime_type(image_path) {
+        user_data.insert("mime_type".to_string(), mime_type);
+    }
+
+    if metadata.len() > FULL_RES_ATTACHMENT_MAX_BYTES {
+        println!("Resizing image to 2MB for {}", image_path);
+        let img = image::open(image_path).map_err(|e| e.to_string())?;
+        let (jpeg_bytes, mime_type) =
+            encode_full_res_under_limit(&img, FULL_RES_ATTACHMENT_MAX_BYTES)?;
+        user_data.insert("mime_type".to_string(), mime_type.to_string());
+        let attachment = store
+            .new_attachment_from_bytes(&jpeg_bytes, user_data)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(Some(attachment));
+    }
+
+    println!("Creating full res attachment for {}", image_path);
+    let attachment = store
+        .new_attachment(image_path, user_data)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(attachment))
+}
+
+fn encode_full_res_under_limit(
+    img: &image::DynamicImage,
+    max_bytes: u64,
+) -> Result<(Vec<u8>, &'static str), String> {
+    let mut quality: u8 = 90;
+    let mut scale: f32 = 1.0;
+    let max_bytes = max_bytes as usize;
+
+    for _attempt in 0..12 {
+        let resized = if (scale - 1.0).abs() < f32::EPSILON {
+            img.clone()
+        } else {
+            let (w, h) = img.dimensions();
+            let target_w = (w as f32 * scale).max(1.0) as u32;
+            let target_h = (h as f32 * scale).max(1.0) as u32;
+            img.resize(target_w, target_h, image::imageops::FilterType::Lanczos3)
+        };
+
+        let mut bytes = Vec::new();
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+            &mut bytes,
+            quality,
+        );
+        encoder
+            .encode_image(&resized)
+            .map_err(|e| e.to_string())?;
+
+        if bytes.len() <= max_bytes {
+            return Ok((bytes, "image/jpeg"));
+        }
+
+        if quality > 50 {
+            quality = quality.saturating_sub(15);
+        } else {
+            scale *= 0.85;
+        }
+    }
+
+    Err("Failed to compress image under 2MB".to_string())
+}
+
+fn guess_mime_type(path: &str) -> Option<String> {
+    let ext = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())?;
+    let mime = match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "tiff" | "tif" => "image/tiff",
+        "heic" => "image/heic",
+        _ => return None,
+    };
+    Some(mime.to_string())
+}
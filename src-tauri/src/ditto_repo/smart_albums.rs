@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use dittolive_ditto::prelude::*;
+use dittolive_ditto::store::StoreObserver;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+use crate::activity::ActivityAction;
+use crate::smart_albums::SmartAlbumRules;
+
+use super::{query_photos, DittoRepository, PhotoPayload, SMART_ALBUMS_COLLECTION, SMART_ALBUMS_EVENT};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SmartAlbumDocument {
+    _id: String,
+    name: String,
+    #[serde(default)]
+    rules: SmartAlbumRules,
+    #[serde(default)]
+    created_by: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmartAlbum {
+    pub id: String,
+    pub name: String,
+    pub rules: SmartAlbumRules,
+    pub created_by: Option<String>,
+    pub created_at: Option<String>,
+}
+
+impl From<SmartAlbumDocument> for SmartAlbum {
+    fn from(doc: SmartAlbumDocument) -> Self {
+        SmartAlbum {
+            id: doc._id,
+            name: doc.name,
+            rules: doc.rules,
+            created_by: doc.created_by,
+            created_at: doc.created_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct SmartAlbumContents {
+    album_id: String,
+    photo_ids: Vec<String>,
+}
+
+impl DittoRepository {
+    pub async fn get_smart_albums(&self) -> Result<Vec<SmartAlbum>, String> {
+        query_smart_albums(self.ditto.as_ref()).await
+    }
+
+    pub async fn save_smart_album(
+        &self,
+        id: Option<String>,
+        name: String,
+        rules: SmartAlbumRules,
+    ) -> Result<SmartAlbum, String> {
+        let existing = match id.as_deref() {
+            Some(id) => self
+                .get_smart_albums()
+                .await?
+                .into_iter()
+                .find(|album| album.id == id),
+            None => None,
+        };
+        let doc = SmartAlbumDocument {
+            _id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name,
+            rules,
+            created_by: existing
+                .as_ref()
+                .and_then(|album| album.created_by.clone())
+                .or_else(|| Some(self.local_peer_key())),
+            created_at: existing
+                .as_ref()
+                .and_then(|album| album.created_at.clone())
+                .or_else(|| Some(chrono::Utc::now().to_rfc3339())),
+        };
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("INSERT INTO {SMART_ALBUMS_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to save smart album: {e}"))?;
+        self.log_activity(
+            ActivityAction::SaveSmartAlbum,
+            vec![doc._id.clone()],
+            Some(doc.name.clone()),
+        )
+        .await;
+        Ok(SmartAlbum::from(doc))
+    }
+
+    pub async fn delete_smart_album(&self, id: &str) -> Result<(), String> {
+        let store = self.ditto.store();
+        store
+            .execute_v2((
+                format!("DELETE FROM {SMART_ALBUMS_COLLECTION} WHERE _id = :id"),
+                serde_json::json!({ "id": id }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to delete smart album: {e}"))?;
+        self.log_activity(ActivityAction::DeleteSmartAlbum, vec![id.to_string()], None)
+            .await;
+        Ok(())
+    }
+
+    pub async fn get_smart_album_photo_ids(&self, id: &str) -> Result<Vec<String>, String> {
+        let album = self
+            .get_smart_albums()
+            .await?
+            .into_iter()
+            .find(|album| album.id == id)
+            .ok_or_else(|| format!("Smart album {id} not found"))?;
+        self.query_photo_ids(&album.rules).await
+    }
+
+    pub async fn query_photo_ids(&self, rules: &SmartAlbumRules) -> Result<Vec<String>, String> {
+        let photos = query_photos(self.ditto.as_ref()).await?;
+        Ok(matching_photo_ids(rules, &photos))
+    }
+}
+
+pub(super) fn install_smart_albums_observer(
+    ditto: Arc<Ditto>,
+    app: &AppHandle,
+) -> Result<Arc<StoreObserver>, String> {
+    let store = ditto.store();
+    let app_handle = app.clone();
+    let ditto_for_task = ditto.clone();
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            if let Err(error) = emit_smart_albums_snapshot(ditto_for_task.as_ref(), &app_handle).await {
+                eprintln!("{error}");
+            }
+        }
+    });
+    let query = format!("SELECT * FROM {SMART_ALBUMS_COLLECTION}");
+    store
+        .register_observer_v2(query, move |_query_result| {
+            let _ = tx.send(());
+        })
+        .map_err(|e| format!("Failed to register smart album observer: {e}"))
+}
+
+pub(super) async fn emit_smart_albums_snapshot(ditto: &Ditto, app: &AppHandle) -> Result<(), String> {
+    let albums = query_smart_albums(ditto).await?;
+    let photos = query_photos(ditto).await?;
+    let payload: Vec<SmartAlbumContents> = albums
+        .into_iter()
+        .map(|album| SmartAlbumContents {
+            photo_ids: matching_photo_ids(&album.rules, &photos),
+            album_id: album.id,
+        })
+        .collect();
+    app.emit(SMART_ALBUMS_EVENT, payload)
+        .map_err(|e| format!("Failed to emit SmartAlbums: {e}"))
+}
+
+async fn query_smart_albums(ditto: &Ditto) -> Result<Vec<SmartAlbum>, String> {
+    let result = ditto
+        .store()
+        .execute_v2(format!("SELECT * FROM {SMART_ALBUMS_COLLECTION}"))
+        .await
+        .map_err(|e| format!("Failed to query Ditto smart albums: {e}"))?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<SmartAlbumDocument>().ok())
+        .map(SmartAlbum::from)
+        .collect())
+}
+
+fn matching_photo_ids(rules: &SmartAlbumRules, photos: &[PhotoPayload]) -> Vec<String> {
+    photos
+        .iter()
+        .filter(|photo| rules.matches(photo))
+        .map(|photo| photo.id.clone())
+        .collect()
+}
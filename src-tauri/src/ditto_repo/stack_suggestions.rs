@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+use crate::stack_suggestions::{suggest_stacks, StackSuggestion};
+
+use super::{query_photos, DittoRepository, PhotoPayload, DISMISSED_STACK_SUGGESTIONS_COLLECTION};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DismissedStackSuggestionDocument {
+    _id: String,
+    photo_ids: Vec<String>,
+    dismissed_by: String,
+    dismissed_at: String,
+}
+
+impl DittoRepository {
+    pub async fn get_stack_suggestions(&self, threshold: u32) -> Result<Vec<StackSuggestion>, String> {
+        // Virtual copies are identical to their source by definition.
+        let photos: Vec<PhotoPayload> = query_photos(self.ditto.as_ref())
+            .await?
+            .into_iter()
+            .filter(|photo| photo.source_photo_id.is_none())
+            .collect();
+        let result = self
+            .ditto
+            .store()
+            .execute_v2(format!("SELECT * FROM {DISMISSED_STACK_SUGGESTIONS_COLLECTION}"))
+            .await
+            .map_err(|e| format!("Failed to query dismissed stack suggestions: {e}"))?;
+        // A group that grew a frame since it was dismissed stays dismissed, so match by members
+        // rather than by suggestion ID.
+        let dismissed: Vec<std::collections::HashSet<String>> = result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<DismissedStackSuggestionDocument>().ok())
+            .map(|doc| doc.photo_ids.into_iter().collect())
+            .collect();
+        Ok(suggest_stacks(&photos, threshold)
+            .into_iter()
+            .filter(|suggestion| {
+                !dismissed.iter().any(|members| {
+                    !members.is_empty()
+                        && members.iter().all(|id| suggestion.photo_ids.contains(id))
+                })
+            })
+            .collect())
+    }
+
+    pub async fn accept_stack_suggestion(
+        &self,
+        photo_ids: Vec<String>,
+        primary_id: String,
+    ) -> Result<String, String> {
+        if !photo_ids.contains(&primary_id) {
+            return Err(format!("Primary {primary_id} is not part of the suggestion"));
+        }
+        let stack_id = uuid::Uuid::new_v4().to_string();
+        self.update_photo_stack(photo_ids, stack_id.clone(), primary_id)
+            .await?;
+        Ok(stack_id)
+    }
+
+    pub async fn dismiss_stack_suggestion(&self, mut photo_ids: Vec<String>) -> Result<(), String> {
+        photo_ids.sort();
+        let doc = DismissedStackSuggestionDocument {
+            _id: crate::stack_suggestions::suggestion_id(&photo_ids),
+            photo_ids: photo_ids.clone(),
+            dismissed_by: self.local_peer_key(),
+            dismissed_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.ditto
+            .store()
+            .execute_v2((
+                format!("INSERT INTO {DISMISSED_STACK_SUGGESTIONS_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                serde_json::json!({ "doc": doc }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to dismiss stack suggestion: {e}"))?;
+        self.log_activity(ActivityAction::DismissStackSuggestion, photo_ids, None)
+            .await;
+        Ok(())
+    }
+}
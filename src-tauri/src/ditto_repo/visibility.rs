@@ -0,0 +1,310 @@
+use dittolive_ditto::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::albums::{query_album_memberships, query_album_visibility};
+use super::{
+    DittoRepository, PhotoDocument, ACTIVITY_COLLECTION, ACTIVITY_VISIBILITY_FILTER, ALBUMS_COLLECTION,
+    ALBUM_MEMBERS_COLLECTION, ALBUM_MEMBER_VISIBILITY_FILTER, ALBUM_VISIBILITY_FILTER,
+    MULTI_PHOTO_SCOPED_COLLECTIONS, PHOTOS_COLLECTION, PHOTO_SCOPED_COLLECTIONS, PHOTO_VISIBILITY_FILTER,
+};
+
+/// Who may see a photo, or a document about one. Public unless every album the photo is in is
+/// restricted; then the viewers of those albums plus the photo's author.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(super) struct PhotoVisibility {
+    #[serde(default)]
+    pub(super) restricted: bool,
+    #[serde(default)]
+    pub(super) visible_to: Vec<String>,
+}
+
+impl PhotoVisibility {
+    /// What membership in an album contributes: its creator and the peers it's shared with.
+    pub(super) fn for_album(created_by: &str, restricted: bool, visible_to: &[String]) -> Self {
+        if !restricted {
+            return PhotoVisibility::default();
+        }
+        let mut visible_to = visible_to.to_vec();
+        visible_to.push(created_by.to_string());
+        visible_to.sort();
+        visible_to.dedup();
+        PhotoVisibility {
+            restricted: true,
+            visible_to,
+        }
+    }
+
+    fn from_albums(albums: &std::collections::HashMap<String, Option<PhotoVisibility>>) -> Self {
+        let entries: Vec<&PhotoVisibility> = albums.values().flatten().collect();
+        if entries.is_empty() || entries.iter().any(|entry| !entry.restricted) {
+            return PhotoVisibility::default();
+        }
+        let mut visible_to: Vec<String> = entries
+            .into_iter()
+            .flat_map(|entry| entry.visible_to.iter().cloned())
+            .collect();
+        visible_to.sort();
+        visible_to.dedup();
+        PhotoVisibility {
+            restricted: true,
+            visible_to,
+        }
+    }
+
+    /// Only the peers allowed by both.
+    pub(super) fn intersect(self, other: PhotoVisibility) -> Self {
+        match (self.restricted, other.restricted) {
+            (false, _) => other,
+            (_, false) => self,
+            (true, true) => PhotoVisibility {
+                restricted: true,
+                visible_to: self
+                    .visible_to
+                    .into_iter()
+                    .filter(|peer| other.visible_to.contains(peer))
+                    .collect(),
+            },
+        }
+    }
+
+    /// A virtual copy shows its source's image, so it is never more visible than the source.
+    fn derive(photo: &PhotoDocument, source: Option<&PhotoDocument>) -> Self {
+        let mut visibility = PhotoVisibility::from_albums(&photo.album_visibility);
+        let mut authors = vec![photo.author_peer_id.clone()];
+        if let Some(source) = source {
+            visibility = visibility.intersect(PhotoVisibility::from_albums(&source.album_visibility));
+            authors.push(source.author_peer_id.clone());
+        }
+        if visibility.restricted {
+            visibility.visible_to.extend(authors.into_iter().flatten());
+            visibility.visible_to.sort();
+            visibility.visible_to.dedup();
+        }
+        visibility
+    }
+}
+
+/// The fields shared by `MULTI_PHOTO_SCOPED_COLLECTIONS`.
+#[derive(Debug, Deserialize)]
+struct MultiPhotoDocument {
+    _id: String,
+    #[serde(default)]
+    photo_ids: Vec<String>,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+impl DittoRepository {
+    /// Copied onto new documents about the photo, so they sync to the same peers it does.
+    pub(super) async fn photo_visibility(&self, photo_id: &str) -> Result<PhotoVisibility, String> {
+        self.photos_visibility(&[photo_id.to_string()]).await
+    }
+
+    pub(super) async fn photos_visibility(&self, ids: &[String]) -> Result<PhotoVisibility, String> {
+        photos_visibility(self.ditto.as_ref(), ids).await
+    }
+}
+
+/// The peers that may see every one of `ids`; IDs that aren't photos don't restrict it.
+pub(super) async fn photos_visibility(ditto: &Ditto, ids: &[String]) -> Result<PhotoVisibility, String> {
+    let result = ditto
+        .store()
+        .execute_v2((
+            format!("SELECT * FROM {PHOTOS_COLLECTION} WHERE array_contains(:ids, _id)"),
+            serde_json::json!({ "ids": ids }),
+        ))
+        .await
+        .map_err(|e| format!("Failed to query Ditto photos: {e}"))?;
+    Ok(result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<PhotoDocument>().ok())
+        .map(|doc| doc.visibility)
+        .fold(PhotoVisibility::default(), PhotoVisibility::intersect))
+}
+
+/// Records each album this peer can see on the photos in it, then re-derives every photo's
+/// visibility and copies it onto the documents about it, intersecting for documents about several
+/// photos. Every peer that sees a photo derives the same result, so any of them may run this; it
+/// only writes what changed. `only` limits this to those photos and their copies.
+pub(super) async fn sync_photo_visibility(
+    ditto: &Ditto,
+    only: Option<&std::collections::HashSet<String>>,
+) -> Result<(), String> {
+    let store = ditto.store();
+    let albums = query_album_visibility(ditto).await?;
+    let memberships = query_album_memberships(ditto).await?;
+    let result = store
+        .execute_v2(format!("SELECT * FROM {PHOTOS_COLLECTION}"))
+        .await
+        .map_err(|e| format!("Failed to query Ditto photos: {e}"))?;
+    let mut photos: std::collections::HashMap<String, PhotoDocument> = result
+        .iter()
+        .filter_map(|item| item.deserialize_value::<PhotoDocument>().ok())
+        .map(|doc| (doc._id.clone(), doc))
+        .collect();
+
+    let in_scope = |photo: &PhotoDocument| match only {
+        None => true,
+        Some(ids) => {
+            ids.contains(&photo._id)
+                || photo.source_photo_id.as_ref().is_some_and(|id| ids.contains(id))
+        }
+    };
+
+    for photo in photos.values_mut().filter(|photo| in_scope(photo)) {
+        let member_of = memberships.get(&photo._id);
+        // The albums it is in, plus those it has left.
+        let mut album_ids: Vec<String> = photo
+            .album_visibility
+            .keys()
+            .chain(member_of.into_iter().flatten())
+            .cloned()
+            .collect();
+        album_ids.sort();
+        album_ids.dedup();
+        for album_id in &album_ids {
+            let Some(album_visibility) = albums.get(album_id) else {
+                continue;
+            };
+            let expected = member_of
+                .is_some_and(|ids| ids.contains(album_id))
+                .then(|| album_visibility.clone());
+            if photo.album_visibility.get(album_id).cloned().flatten() == expected {
+                continue;
+            }
+            // Typed as a map so entries written by different peers merge.
+            store
+                .execute_v2((
+                    format!(
+                        "INSERT INTO COLLECTION {PHOTOS_COLLECTION} (album_visibility MAP) DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"
+                    ),
+                    serde_json::json!({
+                        "doc": { "_id": photo._id, "album_visibility": { album_id: expected } },
+                    }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update photo album visibility: {e}"))?;
+            photo.album_visibility.insert(album_id.clone(), expected);
+        }
+    }
+
+    let changed: Vec<(String, PhotoVisibility)> = photos
+        .values()
+        .filter(|photo| in_scope(photo))
+        .filter_map(|photo| {
+            // A copy whose source isn't here can't be derived; a peer that sees both will.
+            let source = match photo.source_photo_id.as_deref() {
+                Some(source_id) => Some(photos.get(source_id)?),
+                None => None,
+            };
+            let visibility = PhotoVisibility::derive(photo, source);
+            (visibility != photo.visibility).then(|| (photo._id.clone(), visibility))
+        })
+        .collect();
+    let mut changed_ids = std::collections::HashSet::new();
+    for (photo_id, visibility) in changed {
+        let args = serde_json::json!({
+            "restricted": visibility.restricted,
+            "visible_to": visibility.visible_to,
+            "id": photo_id,
+        });
+        store
+            .execute_v2((
+                format!("UPDATE {PHOTOS_COLLECTION} SET restricted = :restricted, visible_to = :visible_to WHERE _id = :id"),
+                args.clone(),
+            ))
+            .await
+            .map_err(|e| format!("Failed to update photo visibility: {e}"))?;
+        for collection in PHOTO_SCOPED_COLLECTIONS {
+            store
+                .execute_v2((
+                    format!("UPDATE {collection} SET restricted = :restricted, visible_to = :visible_to WHERE photo_id = :id"),
+                    args.clone(),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update {collection} visibility: {e}"))?;
+        }
+        if let Some(photo) = photos.get_mut(&photo_id) {
+            photo.visibility = visibility;
+        }
+        changed_ids.insert(photo_id);
+    }
+    if changed_ids.is_empty() {
+        return Ok(());
+    }
+
+    for collection in MULTI_PHOTO_SCOPED_COLLECTIONS {
+        let result = store
+            .execute_v2(format!("SELECT * FROM {collection}"))
+            .await
+            .map_err(|e| format!("Failed to query {collection}: {e}"))?;
+        for doc in result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<MultiPhotoDocument>().ok())
+        {
+            if !doc.photo_ids.iter().any(|id| changed_ids.contains(id)) {
+                continue;
+            }
+            // As with copies, a document whose photos aren't all here is left to a peer that sees them.
+            let Some(visibilities) = doc
+                .photo_ids
+                .iter()
+                .map(|id| photos.get(id).map(|photo| photo.visibility.clone()))
+                .collect::<Option<Vec<_>>>()
+            else {
+                continue;
+            };
+            let visibility = visibilities
+                .into_iter()
+                .fold(PhotoVisibility::default(), PhotoVisibility::intersect);
+            if visibility == doc.visibility {
+                continue;
+            }
+            store
+                .execute_v2((
+                    format!("UPDATE {collection} SET restricted = :restricted, visible_to = :visible_to WHERE _id = :id"),
+                    serde_json::json!({
+                        "restricted": visibility.restricted,
+                        "visible_to": visibility.visible_to,
+                        "id": doc._id,
+                    }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to update {collection} visibility: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Collections that are only synced to the peers allowed to see each document.
+pub(super) fn filtered_collections() -> Vec<(&'static str, &'static str)> {
+    let mut collections = vec![
+        (PHOTOS_COLLECTION, PHOTO_VISIBILITY_FILTER),
+        (ALBUMS_COLLECTION, ALBUM_VISIBILITY_FILTER),
+        (ALBUM_MEMBERS_COLLECTION, ALBUM_MEMBER_VISIBILITY_FILTER),
+        (ACTIVITY_COLLECTION, ACTIVITY_VISIBILITY_FILTER),
+    ];
+    collections.extend(
+        PHOTO_SCOPED_COLLECTIONS
+            .iter()
+            .chain(MULTI_PHOTO_SCOPED_COLLECTIONS)
+            .map(|collection| (*collection, PHOTO_VISIBILITY_FILTER)),
+    );
+    collections
+}
+
+/// Drops local copies of documents that were restricted after they synced here.
+pub(super) async fn evict_hidden_documents(ditto: &Ditto) -> Result<(), String> {
+    let local_peer_key = ditto.presence().graph().local_peer.peer_key_string.clone();
+    for (collection, filter) in filtered_collections() {
+        ditto
+            .store()
+            .execute_v2((
+                format!("EVICT FROM {collection} WHERE NOT ({filter})"),
+                serde_json::json!({ "peer": local_peer_key }),
+            ))
+            .await
+            .map_err(|e| format!("Failed to evict hidden {collection}: {e}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::activity::ActivityAction;
+use crate::votes::{summarize, PeerVote, VoteChoice, VoteFilter, VoteSummary};
+
+use super::visibility::PhotoVisibility;
+use super::{query_photos, DittoRepository, MAX_RATING, VOTES_COLLECTION};
+
+/// One document per photo and peer, so concurrent votes from different peers never collide.
+#[derive(Debug, Deserialize)]
+struct VoteDocument {
+    _id: String,
+    photo_id: String,
+    peer_key: String,
+    #[serde(default)]
+    choice: Option<VoteChoice>,
+    #[serde(default)]
+    rating: Option<u8>,
+}
+
+/// Only the field being cast is written, so a pick doesn't erase the same peer's rating.
+#[derive(Debug, Serialize)]
+struct VoteWrite {
+    _id: String,
+    photo_id: String,
+    peer_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    choice: Option<Option<VoteChoice>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<Option<u8>>,
+    updated_at: String,
+    #[serde(flatten)]
+    visibility: PhotoVisibility,
+}
+
+impl From<VoteDocument> for PeerVote {
+    fn from(doc: VoteDocument) -> Self {
+        PeerVote {
+            photo_id: doc.photo_id,
+            peer_key: doc.peer_key,
+            choice: doc.choice,
+            rating: doc.rating,
+        }
+    }
+}
+
+impl DittoRepository {
+    /// `None` withdraws this peer's pick or reject.
+    pub async fn cast_votes(
+        &self,
+        photo_ids: Vec<String>,
+        choice: Option<VoteChoice>,
+    ) -> Result<(), String> {
+        self.write_votes(photo_ids, Some(choice), None).await
+    }
+
+    /// `None` withdraws this peer's rating.
+    pub async fn cast_ratings(&self, photo_ids: Vec<String>, rating: Option<u8>) -> Result<(), String> {
+        if rating.is_some_and(|rating| rating > MAX_RATING) {
+            return Err(format!("Rating must be between 0 and {MAX_RATING}"));
+        }
+        self.write_votes(photo_ids, None, Some(rating)).await
+    }
+
+    async fn write_votes(
+        &self,
+        photo_ids: Vec<String>,
+        choice: Option<Option<VoteChoice>>,
+        rating: Option<Option<u8>>,
+    ) -> Result<(), String> {
+        let store = self.ditto.store();
+        let peer_key = self.local_peer_key();
+        let updated_at = chrono::Utc::now().to_rfc3339();
+        for photo_id in &photo_ids {
+            let doc = VoteWrite {
+                _id: format!("{photo_id}:{peer_key}"),
+                photo_id: photo_id.clone(),
+                peer_key: peer_key.clone(),
+                choice,
+                rating,
+                updated_at: updated_at.clone(),
+                visibility: self.photo_visibility(photo_id).await?,
+            };
+            store
+                .execute_v2((
+                    format!("INSERT INTO {VOTES_COLLECTION} DOCUMENTS (:doc) ON ID CONFLICT DO UPDATE"),
+                    serde_json::json!({ "doc": doc }),
+                ))
+                .await
+                .map_err(|e| format!("Failed to cast vote: {e}"))?;
+        }
+        let detail = match (choice, rating) {
+            (Some(Some(choice)), _) => serde_json::to_value(choice)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string)),
+            (_, Some(Some(rating))) => Some(format!("rating {rating}")),
+            _ => Some("withdrawn".to_string()),
+        };
+        self.log_activity(ActivityAction::Vote, photo_ids, detail)
+            .await;
+        Ok(())
+    }
+
+    /// Aggregated votes for `photo_ids` (or the whole library), optionally narrowed by `filter`.
+    pub async fn get_vote_summaries(
+        &self,
+        photo_ids: Option<Vec<String>>,
+        filter: Option<VoteFilter>,
+    ) -> Result<Vec<VoteSummary>, String> {
+        let photo_ids = match photo_ids {
+            Some(ids) => ids,
+            None => query_photos(self.ditto.as_ref())
+                .await?
+                .into_iter()
+                .map(|photo| photo.id)
+                .collect(),
+        };
+        let result = self
+            .ditto
+            .store()
+            .execute_v2(format!("SELECT * FROM {VOTES_COLLECTION}"))
+            .await
+            .map_err(|e| format!("Failed to query votes: {e}"))?;
+        let votes: Vec<PeerVote> = result
+            .iter()
+            .filter_map(|item| item.deserialize_value::<VoteDocument>().ok())
+            .map(PeerVote::from)
+            .collect();
+        Ok(summarize(&photo_ids, votes, &self.local_peer_key())
+            .into_iter()
+            .filter(|summary| filter.is_none_or(|filter| summary.matches(filter)))
+            .collect())
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::analysis_cache;
+use crate::ditto_repo::{DittoRepository, PhotoPayload};
+use crate::faces::{analyze_faces, DetectedFace, DEFAULT_MATCH_THRESHOLD};
+
+const FACES_CACHE: &str = "faces";
+
+/// Indexes the faces of every photo whose original lives on this device, again whenever the
+/// original changes. Each trigger runs a pass over the photos not handled yet; triggers arriving
+/// mid-pass collapse into one.
+pub fn spawn(app: AppHandle) -> mpsc::UnboundedSender<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        let mut handled = HashMap::new();
+        while rx.recv().await.is_some() {
+            while rx.try_recv().is_ok() {}
+            if let Err(error) = run_pass(&app, &mut handled).await {
+                eprintln!("Face indexing: {error}");
+            }
+        }
+    });
+    tx
+}
+
+/// `handled` maps photo IDs to the analysis cache fingerprint of the original they were indexed at.
+type Handled = HashMap<String, (Option<u64>, Option<u64>)>;
+
+async fn run_pass(app: &AppHandle, handled: &mut Handled) -> Result<(), String> {
+    if !crate::faces::recognition_enabled() {
+        return Ok(());
+    }
+    // The repository is managed after the first library snapshot, so early triggers are skipped.
+    let Some(repo) = app.try_state::<DittoRepository>() else {
+        return Ok(());
+    };
+    let local_peer_key = repo.local_peer_key();
+    let pending: Vec<PhotoPayload> = repo
+        .get_photos()
+        .await?
+        .into_iter()
+        .filter(|photo| {
+            photo.author_peer_id.as_deref() == Some(local_peer_key.as_str())
+                && photo.source_photo_id.is_none()
+                && handled.get(&photo.id) != Some(&analysis_cache::fingerprint(&photo.image_path))
+        })
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    let indexed: HashSet<String> = repo
+        .get_face_regions(None)
+        .await?
+        .into_iter()
+        .map(|region| region.photo_id)
+        .collect();
+
+    let mut stored = 0;
+    for photo in pending {
+        let cached = cached_faces(&photo);
+        let already_synced = cached
+            .as_ref()
+            .is_some_and(|faces| faces.is_empty() || indexed.contains(&photo.id));
+        if already_synced {
+            handled.insert(photo.id, analysis_cache::fingerprint(&photo.image_path));
+            continue;
+        }
+        let faces = match cached {
+            Some(faces) => faces,
+            None => match analyze_photo(&photo).await {
+                Ok(faces) => faces,
+                Err(error) => {
+                    eprintln!("Face indexing: {}: {error}", photo.image_path);
+                    continue;
+                }
+            },
+        };
+        repo.store_photo_faces(&photo.id, faces).await?;
+        handled.insert(photo.id, analysis_cache::fingerprint(&photo.image_path));
+        stored += 1;
+    }
+    // Matching against confirmed people looks at every face, so it runs once per pass.
+    if stored > 0 {
+        repo.suggest_people(DEFAULT_MATCH_THRESHOLD).await?;
+    }
+    Ok(())
+}
+
+pub fn cached_faces(photo: &PhotoPayload) -> Option<Vec<DetectedFace>> {
+    analysis_cache::get(FACES_CACHE, &photo.id, &photo.image_path)
+}
+
+/// Cache-aware analysis of a local original; runs the models only when the image changed.
+pub async fn analyze_photo(photo: &PhotoPayload) -> Result<Vec<DetectedFace>, String> {
+    if let Some(faces) = cached_faces(photo) {
+        return Ok(faces);
+    }
+    if !std::path::Path::new(&photo.image_path).exists() {
+        return Err(format!("Original of photo {} is not available on this device", photo.id));
+    }
+    let path = photo.image_path.clone();
+    let faces = tauri::async_runtime::spawn_blocking(move || analyze_faces(&path))
+        .await
+        .map_err(|e| format!("Face analysis task failed: {e}"))??;
+    analysis_cache::put(FACES_CACHE, &photo.id, &photo.image_path, &faces)?;
+    Ok(faces)
+}
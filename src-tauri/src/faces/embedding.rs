@@ -0,0 +1,77 @@
+use std::sync::OnceLock;
+
+use image::{DynamicImage, GenericImageView};
+use tract_onnx::prelude::*;
+
+use super::FaceBox;
+
+/// InsightFace MobileFaceNet (buffalo_s `w600k_mbf`), 112x112 RGB in, 512-d embedding out.
+const MODEL_FILE: &str = "w600k_mbf.onnx";
+const INPUT_SIZE: u32 = 112;
+/// Detectors crop tight around the face; ArcFace-style models expect some forehead and chin.
+const CROP_MARGIN: f32 = 0.2;
+
+type EmbeddingModel = TypedRunnableModel<TypedModel>;
+
+static MODEL: OnceLock<Result<EmbeddingModel, String>> = OnceLock::new();
+
+fn model() -> Result<&'static EmbeddingModel, String> {
+    MODEL
+        .get_or_init(load_model)
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+fn load_model() -> Result<EmbeddingModel, String> {
+    let path = crate::models::model_path(MODEL_FILE)?;
+    tract_onnx::onnx()
+        .model_for_path(&path)
+        .and_then(|model| {
+            model.with_input_fact(
+                0,
+                f32::fact([1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize]).into(),
+            )
+        })
+        .and_then(|model| model.into_optimized())
+        .and_then(|model| model.into_runnable())
+        .map_err(|e| format!("Failed to load face embedding model: {e}"))
+}
+
+pub fn embed_face(img: &DynamicImage, face: &FaceBox) -> Result<Vec<f32>, String> {
+    let model = model()?;
+    let crop = crop_face(img, face);
+    let rgb = crop
+        .resize_exact(INPUT_SIZE, INPUT_SIZE, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize),
+        |(_, c, y, x)| (rgb.get_pixel(x as u32, y as u32)[c] as f32 - 127.5) / 127.5,
+    )
+    .into();
+
+    let outputs = model
+        .run(tvec!(input.into()))
+        .map_err(|e| format!("Face embedding failed: {e}"))?;
+    let embedding: Vec<f32> = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .copied()
+        .collect();
+    Ok(super::normalize(embedding))
+}
+
+/// Square crop centred on the face so the model doesn't see a stretched face.
+fn crop_face(img: &DynamicImage, face: &FaceBox) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as f32, height as f32);
+    let center_x = (face.x + face.width / 2.0) * w;
+    let center_y = (face.y + face.height / 2.0) * h;
+    let side = (face.width * w).max(face.height * h) * (1.0 + CROP_MARGIN * 2.0);
+
+    let left = (center_x - side / 2.0).clamp(0.0, w - 1.0) as u32;
+    let top = (center_y - side / 2.0).clamp(0.0, h - 1.0) as u32;
+    let crop_w = (side as u32).clamp(1, width - left);
+    let crop_h = (side as u32).clamp(1, height - top);
+    img.crop_imm(left, top, crop_w, crop_h)
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "face_recognition")]
+mod embedding;
+#[cfg(feature = "cpu_face_detect")]
+mod ultraface;
+#[cfg(all(target_os = "macos", feature = "vision_face_detect"))]
+mod vision;
+
+/// Cosine similarity between two faces of the same person is usually well above this.
+pub const DEFAULT_MATCH_THRESHOLD: f32 = 0.4;
+
+/// Normalized to the image size, origin at the top-left corner.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FaceBox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub confidence: f32,
+}
+
+impl FaceBox {
+    pub fn area(&self) -> f32 {
+        self.width.max(0.0) * self.height.max(0.0)
+    }
+
+    pub fn iou(&self, other: &FaceBox) -> f32 {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        let intersection = (right - left).max(0.0) * (bottom - top).max(0.0);
+        let union = self.area() + other.area() - intersection;
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
+
+/// Vision is preferred on macOS; the CPU detector covers every other platform.
+pub fn detect_faces(path: &str) -> Result<Vec<FaceBox>, String> {
+    #[cfg(all(target_os = "macos", feature = "vision_face_detect"))]
+    {
+        return vision::detect_faces(path);
+    }
+
+    #[cfg(all(
+        feature = "cpu_face_detect",
+        not(all(target_os = "macos", feature = "vision_face_detect"))
+    ))]
+    {
+        let img = image::open(path).map_err(|e| e.to_string())?;
+        return ultraface::detect_faces(&img);
+    }
+
+    #[cfg(not(any(
+        feature = "cpu_face_detect",
+        all(target_os = "macos", feature = "vision_face_detect")
+    )))]
+    {
+        let _ = path;
+        Ok(vec![])
+    }
+}
+
+pub fn contains_face(path: &str) -> Result<bool, String> {
+    Ok(!detect_faces(path)?.is_empty())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DetectedFace {
+    pub bounding_box: FaceBox,
+    /// L2-normalized, so the dot product of two embeddings is their cosine similarity.
+    pub embedding: Vec<f32>,
+}
+
+pub const fn detection_enabled() -> bool {
+    cfg!(any(
+        feature = "cpu_face_detect",
+        all(target_os = "macos", feature = "vision_face_detect")
+    ))
+}
+
+pub const fn recognition_enabled() -> bool {
+    cfg!(feature = "face_recognition")
+}
+
+pub fn analyze_faces(path: &str) -> Result<Vec<DetectedFace>, String> {
+    #[cfg(feature = "face_recognition")]
+    {
+        let boxes = detect_faces(path)?;
+        if boxes.is_empty() {
+            return Ok(vec![]);
+        }
+        let img = image::open(path).map_err(|e| e.to_string())?;
+        boxes
+            .into_iter()
+            .map(|bounding_box| {
+                let embedding = embedding::embed_face(&img, &bounding_box)?;
+                Ok(DetectedFace {
+                    bounding_box,
+                    embedding,
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "face_recognition"))]
+    {
+        let _ = path;
+        Err("Face recognition is not enabled in this build (face_recognition feature)".to_string())
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Best similarity between `face` and any of the reference faces, with the index of that face.
+pub fn best_match(face: &[f32], references: &[Vec<f32>]) -> Option<(usize, f32)> {
+    references
+        .iter()
+        .enumerate()
+        .map(|(idx, reference)| (idx, cosine_similarity(face, reference)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg_attr(not(feature = "face_recognition"), allow(dead_code))]
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Single-link clustering: faces end up together when a chain of pairs is above `threshold`.
+/// Returns a cluster index per embedding.
+pub fn cluster_embeddings(embeddings: &[Vec<f32>], threshold: f32) -> Vec<usize> {
+    let mut parent: Vec<usize> = (0..embeddings.len()).collect();
+
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    for i in 0..embeddings.len() {
+        for j in (i + 1)..embeddings.len() {
+            if cosine_similarity(&embeddings[i], &embeddings[j]) >= threshold {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[b.max(a)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    (0..embeddings.len()).map(|i| find(&mut parent, i)).collect()
+}
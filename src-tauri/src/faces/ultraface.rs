@@ -0,0 +1,249 @@
+use std::sync::OnceLock;
+
+use image::DynamicImage;
+use tract_onnx::prelude::*;
+
+use super::FaceBox;
+
+/// Ultra-Light-Fast-Generic-Face-Detector (RFB, 320x240), exported with box decoding included.
+const MODEL_FILE: &str = "version-RFB-320.onnx";
+const INPUT_WIDTH: u32 = 320;
+const INPUT_HEIGHT: u32 = 240;
+const CONFIDENCE_THRESHOLD: f32 = 0.7;
+const NMS_IOU_THRESHOLD: f32 = 0.3;
+const MAX_FACES: usize = 64;
+
+type FaceModel = TypedRunnableModel<TypedModel>;
+
+static MODEL: OnceLock<Result<FaceModel, String>> = OnceLock::new();
+
+fn model() -> Result<&'static FaceModel, String> {
+    MODEL
+        .get_or_init(load_model)
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+fn load_model() -> Result<FaceModel, String> {
+    let path = crate::models::model_path(MODEL_FILE)?;
+    tract_onnx::onnx()
+        .model_for_path(&path)
+        .and_then(|model| {
+            model.with_input_fact(
+                0,
+                f32::fact([1, 3, INPUT_HEIGHT as usize, INPUT_WIDTH as usize]).into(),
+            )
+        })
+        .and_then(|model| model.into_optimized())
+        .and_then(|model| model.into_runnable())
+        .map_err(|e| format!("Failed to load face detection model: {e}"))
+}
+
+pub fn detect_faces(img: &DynamicImage) -> Result<Vec<FaceBox>, String> {
+    let model = model()?;
+    let rgb = img
+        .resize_exact(INPUT_WIDTH, INPUT_HEIGHT, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, INPUT_HEIGHT as usize, INPUT_WIDTH as usize),
+        |(_, c, y, x)| (rgb.get_pixel(x as u32, y as u32)[c] as f32 - 127.0) / 128.0,
+    )
+    .into();
+
+    let outputs = model
+        .run(tvec!(input.into()))
+        .map_err(|e| format!("Face detection failed: {e}"))?;
+    let scores = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| e.to_string())?;
+    let boxes = outputs[1]
+        .to_array_view::<f32>()
+        .map_err(|e| e.to_string())?;
+
+    let candidates = scores
+        .outer_iter()
+        .next()
+        .zip(boxes.outer_iter().next())
+        .map(|(scores, boxes)| {
+            scores
+                .outer_iter()
+                .zip(boxes.outer_iter())
+                .filter_map(|(score, corners)| {
+                    let confidence = score[1];
+                    if confidence < CONFIDENCE_THRESHOLD {
+                        return None;
+                    }
+                    Some(corners_to_face_box(
+                        [corners[0], corners[1], corners[2], corners[3]],
+                        confidence,
+                    ))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    Ok(non_max_suppression(candidates, NMS_IOU_THRESHOLD, MAX_FACES))
+}
+
+fn corners_to_face_box(corners: [f32; 4], confidence: f32) -> FaceBox {
+    let [x1, y1, x2, y2] = corners.map(|v| v.clamp(0.0, 1.0));
+    FaceBox {
+        x: x1,
+        y: y1,
+        width: (x2 - x1).max(0.0),
+        height: (y2 - y1).max(0.0),
+        confidence,
+    }
+}
+
+fn non_max_suppression(mut candidates: Vec<FaceBox>, iou_threshold: f32, limit: usize) -> Vec<FaceBox> {
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut kept: Vec<FaceBox> = Vec::new();
+    for candidate in candidates {
+        if kept.len() >= limit {
+            break;
+        }
+        if kept.iter().all(|k| k.iou(&candidate) <= iou_threshold) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn face_box(x: f32, y: f32, width: f32, height: f32) -> FaceBox {
+        FaceBox {
+            x,
+            y,
+            width,
+            height,
+            confidence: 1.0,
+        }
+    }
+
+    fn face(x: f32, y: f32, size: f32, confidence: f32) -> FaceBox {
+        FaceBox {
+            x,
+            y,
+            width: size,
+            height: size,
+            confidence,
+        }
+    }
+
+    fn fixtures_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/faces")
+    }
+
+    /// Says whether the detector model is in `models/` (or `PICKSY_MODELS_DIR`), so the model
+    /// tests run wherever it has been fetched and are skipped elsewhere.
+    fn detector_model_available() -> bool {
+        crate::models::init_for_tests();
+        let available = crate::models::model_path(MODEL_FILE).is_ok();
+        if !available {
+            eprintln!("skipping: models/{MODEL_FILE} not found");
+        }
+        available
+    }
+
+    #[derive(serde::Deserialize)]
+    struct ExpectedBox {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    }
+
+    /// Boxes from the fixture's `.json` sidecar, if it has one.
+    fn expected_boxes(image_path: &std::path::Path) -> Option<Vec<FaceBox>> {
+        let json = std::fs::read_to_string(image_path.with_extension("json")).ok()?;
+        let boxes: Vec<ExpectedBox> = serde_json::from_str(&json).unwrap();
+        Some(
+            boxes
+                .into_iter()
+                .map(|b| face_box(b.x, b.y, b.width, b.height))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn nms_keeps_highest_confidence_of_overlapping_boxes() {
+        let kept = non_max_suppression(
+            vec![
+                face(0.10, 0.10, 0.20, 0.80),
+                face(0.11, 0.11, 0.20, 0.95),
+                face(0.60, 0.60, 0.20, 0.75),
+            ],
+            NMS_IOU_THRESHOLD,
+            MAX_FACES,
+        );
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].confidence, 0.95);
+        assert_eq!(kept[1].confidence, 0.75);
+    }
+
+    #[test]
+    fn nms_respects_limit() {
+        let candidates = (0..10)
+            .map(|i| face(i as f32 * 0.1, 0.0, 0.05, 0.9))
+            .collect();
+        assert_eq!(non_max_suppression(candidates, NMS_IOU_THRESHOLD, 3).len(), 3);
+    }
+
+    #[test]
+    fn corners_are_clamped_to_the_image() {
+        let face = corners_to_face_box([-0.1, 0.2, 0.5, 1.3], 0.9);
+        assert_eq!(face.x, 0.0);
+        assert_eq!(face.y, 0.2);
+        assert_eq!(face.width, 0.5);
+        assert!((face.height - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn blank_image_has_no_faces() {
+        if !detector_model_available() {
+            return;
+        }
+        let img = DynamicImage::new_rgb8(640, 480);
+        assert!(detect_faces(&img).unwrap().is_empty());
+    }
+
+    /// Fixtures are named `<expected face count>_<description>.jpg`, see tests/fixtures/faces/README.md.
+    #[test]
+    fn detects_expected_faces_in_fixtures() {
+        if !detector_model_available() {
+            return;
+        }
+        let mut checked = 0;
+        for entry in std::fs::read_dir(fixtures_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                continue;
+            }
+            let Some(expected) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.split('_').next())
+                .and_then(|n| n.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let img = image::open(&path).unwrap();
+            let faces = detect_faces(&img).unwrap();
+            assert_eq!(faces.len(), expected, "{}", path.to_string_lossy());
+            for expected_box in expected_boxes(&path).unwrap_or_default() {
+                assert!(
+                    faces.iter().any(|face| face.iou(&expected_box) >= 0.4),
+                    "{}: no face detected near {expected_box:?}, got {faces:?}",
+                    path.to_string_lossy()
+                );
+            }
+            checked += 1;
+        }
+        assert!(checked > 0, "no face fixtures found");
+    }
+}
@@ -0,0 +1,50 @@
+use objc2::rc::Retained;
+use objc2_foundation::{NSData, NSDataReadingOptions, NSDictionary, NSString, NSURL};
+use objc2_vision::{VNDetectFaceRectanglesRequest, VNImageRequestHandler, VNRequest};
+
+use super::FaceBox;
+
+pub fn detect_faces(path: &str) -> Result<Vec<FaceBox>, String> {
+    unsafe {
+        let nsurl = NSURL::fileURLWithPath_isDirectory(&NSString::from_str(path), false);
+        let data = NSData::initWithContentsOfURL_options_error(
+            NSData::alloc(),
+            &nsurl,
+            NSDataReadingOptions(0),
+        )
+        .map_err(|_| "Failed to read image data".to_string())?;
+
+        let opts = NSDictionary::new();
+        let handler = VNImageRequestHandler::initWithData_options(
+            VNImageRequestHandler::alloc(),
+            &data,
+            &opts,
+        );
+
+        let req = VNDetectFaceRectanglesRequest::new();
+        let mut reqs: [Retained<VNRequest>; 1] = [Retained::cast(req.retain())];
+
+        let _ = handler
+            .performRequests_error(&mut reqs, std::ptr::null_mut())
+            .map_err(|_| "Vision face detection failed".to_string())?;
+
+        let Some(results) = req.results() else {
+            return Ok(vec![]);
+        };
+
+        // Vision reports normalized rects with the origin at the bottom-left.
+        Ok(results
+            .iter()
+            .map(|observation| {
+                let rect = observation.boundingBox();
+                FaceBox {
+                    x: rect.origin.x as f32,
+                    y: (1.0 - rect.origin.y - rect.size.height) as f32,
+                    width: rect.size.width as f32,
+                    height: rect.size.height as f32,
+                    confidence: observation.confidence(),
+                }
+            })
+            .collect())
+    }
+}
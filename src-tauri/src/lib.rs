@@ -1,4 +1,26 @@
+mod activity;
+mod analysis_cache;
+mod auto_tagging;
+mod color_palette;
+mod culling;
 mod ditto_repo;
+mod face_indexer;
+mod faces;
+mod metadata;
+mod models;
+mod perceptual_hash;
+mod quality;
+mod quality_indexer;
+mod rejects;
+mod semantic_indexer;
+mod semantic_search;
+mod similarity_index;
+mod smart_albums;
+mod stack_suggestions;
+mod tags;
+mod thumbnails;
+mod vector_index;
+mod votes;
 
 use ditto_repo::{AppState, DittoRepository};
 use tauri::{Manager, State};
@@ -12,19 +34,102 @@ mod commands;
 use commands::photo_library_commands::{
     add_photos_to_library,
     analyze_image_metadata,
-    recognize_faces,
     clear_library,
+    create_virtual_copy,
     get_photos_from_library,
     remove_image_from_album,
     add_photos_from_folder,
     save_photo_config,
+    get_config_history,
+    undo_photo_config,
+    redo_photo_config,
+    revert_photo_config,
     set_photo_favorite,
     set_photos_favorite,
+    set_photo_rating,
+    set_photos_rating,
+    set_photo_color_labels,
+    set_photos_color_labels,
     set_photo_stack,
     set_stack_primary,
     clear_photo_stack,
     get_full_res_attachment,
 };
+use commands::activity_commands::get_activity;
+use commands::album_commands::{
+    add_photos_to_album,
+    create_album,
+    delete_album,
+    get_albums,
+    remove_photos_from_album,
+    reorder_album,
+    set_album_visibility,
+    update_album,
+};
+use commands::annotation_commands::{
+    add_annotation,
+    delete_annotation,
+    get_annotations,
+    get_unresolved_annotations,
+    set_annotation_status,
+};
+use commands::comment_commands::{add_comment, delete_comment, edit_comment, get_comments};
+use commands::cull_commands::{
+    create_cull_session,
+    delete_cull_session,
+    get_cull_progress,
+    get_cull_results,
+    get_cull_sessions,
+    record_cull_choice,
+};
+use commands::face_commands::{
+    cluster_faces,
+    confirm_face_regions,
+    get_face_clusters,
+    get_face_regions,
+    get_people,
+    index_photo_faces,
+    merge_people,
+    name_face_cluster,
+    recognize_faces,
+    reject_face_regions,
+    split_face_regions,
+};
+use commands::metadata_commands::{
+    set_photos_location,
+    shift_photos_capture_time,
+    sync_photos_capture_time,
+};
+use commands::quality_commands::{rank_stack, score_photos};
+use commands::reject_commands::{purge_rejected, set_photo_rejected, set_photos_rejected};
+use commands::search_commands::{
+    find_related_photos,
+    get_machine_tags,
+    search_machine_tags,
+    semantic_search,
+};
+use commands::similarity_commands::{
+    accept_stack_suggestion,
+    dismiss_stack_suggestion,
+    find_similar_photos,
+    get_stack_suggestions,
+    search_photos_by_color,
+};
+use commands::smart_album_commands::{
+    delete_smart_album,
+    get_smart_album_photo_ids,
+    get_smart_albums,
+    query_photo_ids,
+    save_smart_album,
+};
+use commands::tag_commands::{
+    add_photos_tags,
+    get_tag_counts,
+    merge_tags,
+    remove_photos_tags,
+    rename_tag,
+};
+use commands::vote_commands::{cast_ratings, cast_votes, get_vote_summaries};
 
 #[tauri::command]
 fn get_app_state(repo: State<'_, DittoRepository>) -> AppState {
@@ -36,12 +141,17 @@ pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
             let handle = app.handle();
+            models::init(handle);
             let repo = tauri::async_runtime::block_on(DittoRepository::init(&handle)).map_err(
                 |e| -> Box<dyn std::error::Error> {
                     Box::new(std::io::Error::new(std::io::ErrorKind::Other, e))
                 },
             )?;
             app.manage(repo);
+            app.state::<DittoRepository>().request_face_indexing();
+            app.state::<DittoRepository>().request_semantic_indexing();
+            app.state::<DittoRepository>().request_similarity_indexing();
+            app.state::<DittoRepository>().request_quality_indexing();
 
             let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&quit_i])?;
@@ -72,16 +182,89 @@ pub fn run() {
             analyze_image_metadata,
             recognize_faces,
             clear_library,
+            create_virtual_copy,
             remove_image_from_album,
             get_app_state,
             get_photos_from_library,
             save_photo_config,
+            get_config_history,
+            undo_photo_config,
+            redo_photo_config,
+            revert_photo_config,
             set_photo_favorite,
             set_photos_favorite,
+            set_photo_rating,
+            set_photos_rating,
+            set_photo_color_labels,
+            set_photos_color_labels,
             set_photo_stack,
             set_stack_primary,
             clear_photo_stack,
-            get_full_res_attachment
+            get_full_res_attachment,
+            shift_photos_capture_time,
+            sync_photos_capture_time,
+            set_photos_location,
+            get_smart_albums,
+            save_smart_album,
+            delete_smart_album,
+            get_smart_album_photo_ids,
+            query_photo_ids,
+            index_photo_faces,
+            get_face_regions,
+            get_people,
+            cluster_faces,
+            get_face_clusters,
+            name_face_cluster,
+            confirm_face_regions,
+            merge_people,
+            split_face_regions,
+            reject_face_regions,
+            find_similar_photos,
+            get_stack_suggestions,
+            accept_stack_suggestion,
+            dismiss_stack_suggestion,
+            score_photos,
+            rank_stack,
+            search_photos_by_color,
+            semantic_search,
+            find_related_photos,
+            get_machine_tags,
+            search_machine_tags,
+            set_photo_rejected,
+            set_photos_rejected,
+            purge_rejected,
+            cast_votes,
+            cast_ratings,
+            get_vote_summaries,
+            get_comments,
+            add_comment,
+            edit_comment,
+            delete_comment,
+            get_annotations,
+            get_unresolved_annotations,
+            add_annotation,
+            set_annotation_status,
+            delete_annotation,
+            add_photos_tags,
+            remove_photos_tags,
+            rename_tag,
+            merge_tags,
+            get_tag_counts,
+            get_albums,
+            create_album,
+            update_album,
+            set_album_visibility,
+            delete_album,
+            add_photos_to_album,
+            remove_photos_from_album,
+            reorder_album,
+            get_cull_sessions,
+            create_cull_session,
+            delete_cull_session,
+            get_cull_progress,
+            record_cull_choice,
+            get_cull_results,
+            get_activity
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,258 @@
+use chrono::{Duration, NaiveDateTime};
+use rexif::{ExifTag, TagValue};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::ditto_repo::ImageMetadata;
+
+const EXIF_DATETIME_FORMAT: &str = "%Y:%m:%d %H:%M:%S";
+const CAPTURE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const SIDECAR_CREATOR_TOOL: &str = "Picksy";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataWriteTarget {
+    Sidecar,
+    Exif,
+}
+
+pub fn read_image_metadata(path: &str) -> ImageMetadata {
+    let mut out = ImageMetadata::default();
+
+    let data = match rexif::parse_file(path) {
+        Ok(d) => d,
+        Err(_) => return out,
+    };
+
+    for entry in &data.entries {
+        match entry.tag {
+            ExifTag::DateTimeOriginal | ExifTag::DateTime => {
+                if out.datetime.is_none() {
+                    let raw = entry.value_more_readable.to_string();
+                    out.datetime = Some(normalize_capture_time(&raw).unwrap_or(raw));
+                }
+            }
+            ExifTag::Make => out.make = Some(entry.value_more_readable.to_string()),
+            ExifTag::Model => out.model = Some(entry.value_more_readable.to_string()),
+            ExifTag::GPSLatitude => out.latitude = parse_gps_to_decimal(&entry.value),
+            ExifTag::GPSLongitude => out.longitude = parse_gps_to_decimal(&entry.value),
+            ExifTag::GPSLatitudeRef => {
+                if entry.value_more_readable.contains('S') {
+                    out.latitude = out.latitude.map(|lat| -lat.abs());
+                }
+            }
+            ExifTag::GPSLongitudeRef => {
+                if entry.value_more_readable.contains('W') {
+                    out.longitude = out.longitude.map(|lon| -lon.abs());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out
+}
+
+fn parse_gps_to_decimal(value: &TagValue) -> Option<f64> {
+    if let TagValue::URational(values) = value {
+        if values.len() >= 3 {
+            let deg = values[0].value();
+            let min = values[1].value();
+            let sec = values[2].value();
+            return Some(deg + (min / 60.0) + (sec / 3600.0));
+        }
+    }
+    None
+}
+
+/// Accepts both the EXIF (`2024:05:01 12:00:00`) and the stored ISO-like format.
+pub fn parse_capture_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, CAPTURE_TIME_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(value, EXIF_DATETIME_FORMAT))
+        .ok()
+}
+
+pub fn format_capture_time(value: &NaiveDateTime) -> String {
+    value.format(CAPTURE_TIME_FORMAT).to_string()
+}
+
+fn normalize_capture_time(value: &str) -> Option<String> {
+    parse_capture_time(value).map(|t| format_capture_time(&t))
+}
+
+pub fn shift_capture_time(value: &str, offset_seconds: i64) -> Option<String> {
+    let parsed = parse_capture_time(value)?;
+    let shifted = parsed.checked_add_signed(Duration::seconds(offset_seconds))?;
+    Some(format_capture_time(&shifted))
+}
+
+pub fn capture_time_offset_seconds(from: &str, to: &str) -> Option<i64> {
+    let from = parse_capture_time(from)?;
+    let to = parse_capture_time(to)?;
+    Some((to - from).num_seconds())
+}
+
+/// Tags only go into sidecars; EXIF has no keyword field other tools agree on.
+pub fn write_metadata_to_original(
+    image_path: &str,
+    metadata: &ImageMetadata,
+    tags: &[String],
+    target: MetadataWriteTarget,
+) -> Result<(), String> {
+    match target {
+        MetadataWriteTarget::Sidecar => write_xmp_sidecar(image_path, metadata, tags),
+        MetadataWriteTarget::Exif => write_exif(image_path, metadata),
+    }
+}
+
+pub fn sidecar_path(image_path: &str) -> PathBuf {
+    Path::new(image_path).with_extension("xmp")
+}
+
+fn write_xmp_sidecar(image_path: &str, metadata: &ImageMetadata, tags: &[String]) -> Result<(), String> {
+    let path = sidecar_path(image_path);
+
+    // Sidecars written by other tools (Lightroom, darktable, ...) carry edits we can't merge.
+    if path.exists() {
+        let existing = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        if !existing.contains(&format!("xmp:CreatorTool=\"{SIDECAR_CREATOR_TOOL}\"")) {
+            return Err(format!(
+                "Refusing to overwrite sidecar not written by {SIDECAR_CREATOR_TOOL}: {}",
+                path.to_string_lossy()
+            ));
+        }
+    }
+
+    let mut attributes = vec![format!("xmp:CreatorTool=\"{SIDECAR_CREATOR_TOOL}\"")];
+    if let Some(datetime) = metadata.datetime.as_deref().and_then(parse_capture_time) {
+        attributes.push(format!(
+            "exif:DateTimeOriginal=\"{}\"",
+            format_capture_time(&datetime)
+        ));
+    }
+    if let Some(latitude) = metadata.latitude {
+        attributes.push(format!(
+            "exif:GPSLatitude=\"{}\"",
+            format_xmp_gps(latitude, 'N', 'S')
+        ));
+    }
+    if let Some(longitude) = metadata.longitude {
+        attributes.push(format!(
+            "exif:GPSLongitude=\"{}\"",
+            format_xmp_gps(longitude, 'E', 'W')
+        ));
+    }
+
+    let xmp = format!(
+        r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:lr="http://ns.adobe.com/lightroom/1.0/"
+    {}>{}
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>
+"#,
+        attributes.join("\n    "),
+        xmp_keywords(tags)
+    );
+
+    std::fs::write(&path, xmp).map_err(|e| format!("Failed to write sidecar: {e}"))
+}
+
+/// Lightroom's layout: every level as a flat `dc:subject` keyword, the full paths
+/// `|`-separated in `lr:hierarchicalSubject`.
+fn xmp_keywords(tags: &[String]) -> String {
+    if tags.is_empty() {
+        return String::new();
+    }
+    let keywords = crate::tags::dedup_tags(
+        tags.iter()
+            .flat_map(|tag| tag.split(crate::tags::TAG_SEPARATOR).map(str::to_string))
+            .collect(),
+    );
+    let hierarchical = crate::tags::dedup_tags(
+        tags.iter()
+            .flat_map(|tag| crate::tags::tag_ancestors(tag))
+            .map(|tag| tag.replace(crate::tags::TAG_SEPARATOR, "|"))
+            .collect(),
+    );
+    let items = |values: &[String]| {
+        values
+            .iter()
+            .map(|value| format!("\n      <rdf:li>{}</rdf:li>", escape_xml(value)))
+            .collect::<String>()
+    };
+    format!(
+        "\n   <dc:subject>\n    <rdf:Bag>{}\n    </rdf:Bag>\n   </dc:subject>\n   <lr:hierarchicalSubject>\n    <rdf:Bag>{}\n    </rdf:Bag>\n   </lr:hierarchicalSubject>",
+        items(&keywords),
+        items(&hierarchical)
+    )
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// XMP stores coordinates as `DDD,MM.mmmmmmR`.
+fn format_xmp_gps(value: f64, positive: char, negative: char) -> String {
+    let reference = if value < 0.0 { negative } else { positive };
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes = (value - degrees) * 60.0;
+    format!("{},{:.6}{}", degrees as u32, minutes, reference)
+}
+
+fn write_exif(image_path: &str, metadata: &ImageMetadata) -> Result<(), String> {
+    use little_exif::exif_tag::ExifTag as WriteTag;
+    use little_exif::metadata::Metadata;
+
+    let path = Path::new(image_path);
+    let mut exif = Metadata::new_from_path(path)
+        .map_err(|e| format!("Failed to read EXIF from {image_path}: {e}"))?;
+
+    if let Some(datetime) = metadata.datetime.as_deref().and_then(parse_capture_time) {
+        let value = datetime.format(EXIF_DATETIME_FORMAT).to_string();
+        exif.set_tag(WriteTag::DateTimeOriginal(value.clone()));
+        exif.set_tag(WriteTag::CreateDate(value));
+    }
+    if let Some(latitude) = metadata.latitude {
+        let reference = if latitude < 0.0 { "S" } else { "N" };
+        exif.set_tag(WriteTag::GPSLatitudeRef(reference.to_string()));
+        exif.set_tag(WriteTag::GPSLatitude(decimal_to_exif_rationals(latitude)));
+    }
+    if let Some(longitude) = metadata.longitude {
+        let reference = if longitude < 0.0 { "W" } else { "E" };
+        exif.set_tag(WriteTag::GPSLongitudeRef(reference.to_string()));
+        exif.set_tag(WriteTag::GPSLongitude(decimal_to_exif_rationals(longitude)));
+    }
+
+    exif.write_to_file(path)
+        .map_err(|e| format!("Failed to write EXIF to {image_path}: {e}"))
+}
+
+fn decimal_to_exif_rationals(value: f64) -> Vec<little_exif::rational::uR64> {
+    use little_exif::rational::uR64;
+
+    let value = value.abs();
+    let degrees = value.trunc();
+    let minutes_full = (value - degrees) * 60.0;
+    let minutes = minutes_full.trunc();
+    let seconds = (minutes_full - minutes) * 60.0;
+
+    vec![
+        uR64 { nominator: degrees as u32, denominator: 1 },
+        uR64 { nominator: minutes as u32, denominator: 1 },
+        uR64 { nominator: (seconds * 10_000.0).round() as u32, denominator: 10_000 },
+    ]
+}
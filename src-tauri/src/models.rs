@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager};
+
+const MODELS_DIR_ENV: &str = "PICKSY_MODELS_DIR";
+
+static MODELS_DIRS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+/// Model files are never downloaded at runtime. Permissively licensed ones are bundled as
+/// resources; the rest are dropped into `models` under the app data directory by the user.
+/// `PICKSY_MODELS_DIR` overrides both during development.
+pub fn init(app: &AppHandle) {
+    let dirs: Vec<PathBuf> = [
+        std::env::var(MODELS_DIR_ENV).map(PathBuf::from).ok(),
+        app.path().app_data_dir().ok().map(|dir| dir.join("models")),
+        app.path().resolve("models", BaseDirectory::Resource).ok(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    for dir in &dirs {
+        println!("Models: searching directory {}", dir.to_string_lossy());
+    }
+    let _ = MODELS_DIRS.set(dirs);
+}
+
+/// What tests search instead of the app directories: `PICKSY_MODELS_DIR`, then the crate's own
+/// `models/`. Setting it once avoids changing the environment under tests running in parallel.
+#[cfg(test)]
+pub fn init_for_tests() {
+    let dirs = [
+        std::env::var(MODELS_DIR_ENV).map(PathBuf::from).ok(),
+        Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("models")),
+    ];
+    let _ = MODELS_DIRS.set(dirs.into_iter().flatten().collect());
+}
+
+pub fn model_path(file_name: &str) -> Result<PathBuf, String> {
+    let dirs = MODELS_DIRS
+        .get()
+        .cloned()
+        .or_else(|| {
+            std::env::var(MODELS_DIR_ENV)
+                .map(|dir| vec![PathBuf::from(dir)])
+                .ok()
+        })
+        .ok_or_else(|| format!("Models directory not configured (set {MODELS_DIR_ENV})"))?;
+    dirs.iter()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("Model file not found: {file_name}"))
+}
@@ -0,0 +1,109 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+use crate::ditto_repo::PhotoPayload;
+
+const DCT_SIZE: usize = 32;
+const HASH_SIZE: usize = 8;
+
+/// 64-bit hashes as hex strings; Ditto stores numbers as doubles, which can't hold a full u64.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PerceptualHash {
+    pub phash: String,
+    pub dhash: String,
+}
+
+impl PerceptualHash {
+    pub fn compute(img: &DynamicImage) -> Self {
+        PerceptualHash {
+            phash: format!("{:016x}", phash(img)),
+            dhash: format!("{:016x}", dhash(img)),
+        }
+    }
+
+    pub fn phash_bits(&self) -> Option<u64> {
+        u64::from_str_radix(&self.phash, 16).ok()
+    }
+
+    pub fn dhash_bits(&self) -> Option<u64> {
+        u64::from_str_radix(&self.dhash, 16).ok()
+    }
+
+    /// pHash carries the decision; dHash only breaks ties between equally distant photos.
+    pub fn distance(&self, other: &PerceptualHash) -> Option<(u32, u32)> {
+        let phash = (self.phash_bits()? ^ other.phash_bits()?).count_ones();
+        let dhash = (self.dhash_bits()? ^ other.dhash_bits()?).count_ones();
+        Some((phash, dhash))
+    }
+}
+
+/// Photos imported before hashing existed only have their thumbnail to go on.
+pub fn photo_hash(photo: &PhotoPayload) -> Option<PerceptualHash> {
+    photo.perceptual_hash.clone().or_else(|| {
+        crate::thumbnails::decode_thumbnail(&photo.base64).map(|img| PerceptualHash::compute(&img))
+    })
+}
+
+/// Difference hash: is each pixel brighter than its right-hand neighbour on a 9x8 grid.
+fn dhash(img: &DynamicImage) -> u64 {
+    let gray = img
+        .resize_exact(HASH_SIZE as u32 + 1, HASH_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..HASH_SIZE as u32 {
+        for x in 0..HASH_SIZE as u32 {
+            hash <<= 1;
+            if gray.get_pixel(x, y)[0] > gray.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// DCT hash: low-frequency 8x8 block of a 32x32 DCT, thresholded at its median.
+fn phash(img: &DynamicImage) -> u64 {
+    let gray = img
+        .resize_exact(DCT_SIZE as u32, DCT_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = gray.pixels().map(|p| p[0] as f64).collect();
+    let coefficients = dct_2d(&pixels, DCT_SIZE);
+
+    let mut low: Vec<f64> = Vec::with_capacity(HASH_SIZE * HASH_SIZE);
+    for y in 0..HASH_SIZE {
+        for x in 0..HASH_SIZE {
+            low.push(coefficients[y * DCT_SIZE + x]);
+        }
+    }
+    // The DC term is the average brightness and would skew the median.
+    let mut sorted: Vec<f64> = low[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    low.iter()
+        .fold(0u64, |hash, value| (hash << 1) | u64::from(*value > median))
+}
+
+fn dct_2d(input: &[f64], n: usize) -> Vec<f64> {
+    let cos: Vec<f64> = (0..n * n)
+        .map(|i| {
+            let (k, x) = (i / n, i % n);
+            (std::f64::consts::PI / n as f64 * (x as f64 + 0.5) * k as f64).cos()
+        })
+        .collect();
+
+    let mut rows = vec![0.0; n * n];
+    for y in 0..n {
+        for k in 0..n {
+            rows[y * n + k] = (0..n).map(|x| input[y * n + x] * cos[k * n + x]).sum();
+        }
+    }
+    let mut out = vec![0.0; n * n];
+    for x in 0..n {
+        for k in 0..n {
+            out[k * n + x] = (0..n).map(|y| rows[y * n + x] * cos[k * n + y]).sum();
+        }
+    }
+    out
+}
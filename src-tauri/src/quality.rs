@@ -0,0 +1,135 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, GrayImage};
+use serde::{Deserialize, Serialize};
+
+use crate::ditto_repo::PhotoPayload;
+
+/// Analysis runs on a downscaled copy so scores are comparable across camera resolutions.
+const ANALYSIS_MAX_SIDE: u32 = 1024;
+/// Laplacian variance at which a frame counts as fully sharp.
+const SHARP_VARIANCE: f32 = 800.0;
+/// Noise sigma (8-bit levels) at which the noise score bottoms out.
+const MAX_NOISE_SIGMA: f32 = 12.0;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QualityScore {
+    /// Variance of the Laplacian; higher is sharper.
+    pub sharpness: f32,
+    /// Fraction of pixels crushed to black or blown to white.
+    pub exposure_clipping: f32,
+    /// Estimated noise standard deviation in 8-bit levels.
+    pub noise: f32,
+    #[serde(default)]
+    pub face_count: Option<u32>,
+    /// Weighted combination of the above in `0.0..=1.0`; the value to sort by.
+    pub overall: f32,
+}
+
+pub fn compute(img: &DynamicImage, face_count: Option<u32>) -> QualityScore {
+    let gray = analysis_image(img);
+    let sharpness = laplacian_variance(&gray);
+    let exposure_clipping = clipped_fraction(&gray);
+    let noise = noise_sigma(&gray);
+
+    let sharpness_score = (sharpness / SHARP_VARIANCE).min(1.0);
+    let exposure_score = (1.0 - exposure_clipping * 5.0).clamp(0.0, 1.0);
+    let noise_score = (1.0 - noise / MAX_NOISE_SIGMA).clamp(0.0, 1.0);
+    let mut overall = 0.6 * sharpness_score + 0.25 * exposure_score + 0.15 * noise_score;
+    if face_count.is_some_and(|count| count > 0) {
+        overall = (overall + 0.05).min(1.0);
+    }
+
+    QualityScore {
+        sharpness,
+        exposure_clipping,
+        noise,
+        face_count,
+        overall,
+    }
+}
+
+/// Faces found by whichever detector is compiled in; `None` when there is none.
+pub fn face_count(path: &str) -> Option<u32> {
+    if !crate::faces::detection_enabled() {
+        return None;
+    }
+    crate::faces::detect_faces(path)
+        .ok()
+        .map(|faces| faces.len() as u32)
+}
+
+pub fn score_file(path: &str) -> Result<QualityScore, String> {
+    let img = image::open(path).map_err(|e| format!("Failed to open {path}: {e}"))?;
+    Ok(compute(&img, face_count(path)))
+}
+
+/// The stored score, if the photo has been scored. Thumbnails aren't scored as a fallback:
+/// sharpness and noise depend on resolution, so a 300px estimate would outrank real scores.
+pub fn photo_quality(photo: &PhotoPayload) -> Option<QualityScore> {
+    photo.quality.clone()
+}
+
+fn analysis_image(img: &DynamicImage) -> GrayImage {
+    let (width, height) = img.dimensions();
+    if width.max(height) > ANALYSIS_MAX_SIDE {
+        img.resize(ANALYSIS_MAX_SIDE, ANALYSIS_MAX_SIDE, FilterType::Triangle)
+            .to_luma8()
+    } else {
+        img.to_luma8()
+    }
+}
+
+fn laplacian_variance(gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let px = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    let mut count = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let value = px(x - 1, y) + px(x + 1, y) + px(x, y - 1) + px(x, y + 1) - 4.0 * px(x, y);
+            sum += value;
+            sum_sq += value * value;
+            count += 1.0;
+        }
+    }
+    let mean = sum / count;
+    (sum_sq / count - mean * mean) as f32
+}
+
+fn clipped_fraction(gray: &GrayImage) -> f32 {
+    let total = gray.pixels().len().max(1);
+    let clipped = gray
+        .pixels()
+        .filter(|p| p[0] <= 2 || p[0] >= 253)
+        .count();
+    clipped as f32 / total as f32
+}
+
+/// Immerkær's fast noise variance estimation.
+fn noise_sigma(gray: &GrayImage) -> f32 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+    let px = |x: u32, y: u32| gray.get_pixel(x, y)[0] as f64;
+    let mut sum = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let value = px(x - 1, y - 1) - 2.0 * px(x, y - 1) + px(x + 1, y - 1)
+                - 2.0 * px(x - 1, y)
+                + 4.0 * px(x, y)
+                - 2.0 * px(x + 1, y)
+                + px(x - 1, y + 1)
+                - 2.0 * px(x, y + 1)
+                + px(x + 1, y + 1);
+            sum += value.abs();
+        }
+    }
+    let sigma = sum * (std::f64::consts::PI / 2.0).sqrt()
+        / (6.0 * (width - 2) as f64 * (height - 2) as f64);
+    sigma as f32
+}
@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::ditto_repo::{DittoRepository, PhotoPayload};
+
+/// Scores every photo whose original lives on this device and has no score yet, so imports don't
+/// wait on face detection and full-size analysis. Photo IDs are content IDs, so a changed
+/// original comes back as a new, unscored photo. Triggers arriving mid-pass collapse into one.
+pub fn spawn(app: AppHandle) -> mpsc::UnboundedSender<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        let mut handled = HashSet::new();
+        while rx.recv().await.is_some() {
+            while rx.try_recv().is_ok() {}
+            if let Err(error) = run_pass(&app, &mut handled).await {
+                eprintln!("Quality scoring: {error}");
+            }
+        }
+    });
+    tx
+}
+
+async fn run_pass(app: &AppHandle, handled: &mut HashSet<String>) -> Result<(), String> {
+    let Some(repo) = app.try_state::<DittoRepository>() else {
+        return Ok(());
+    };
+    let local_peer_key = repo.local_peer_key();
+    let pending: Vec<PhotoPayload> = repo
+        .get_photos()
+        .await?
+        .into_iter()
+        .filter(|photo| {
+            photo.author_peer_id.as_deref() == Some(local_peer_key.as_str())
+                && photo.source_photo_id.is_none()
+                && photo.quality.is_none()
+                && !handled.contains(&photo.id)
+        })
+        .collect();
+
+    let mut scored = 0;
+    for photo in pending {
+        // Failures aren't retried until the next launch; `score_photos` rescores on request.
+        handled.insert(photo.id.clone());
+        if !std::path::Path::new(&photo.image_path).exists() {
+            continue;
+        }
+        let path = photo.image_path.clone();
+        let quality = match tauri::async_runtime::spawn_blocking(move || crate::quality::score_file(&path))
+            .await
+            .map_err(|e| format!("Quality scoring task failed: {e}"))
+            .and_then(|result| result)
+        {
+            Ok(quality) => quality,
+            Err(error) => {
+                eprintln!("Quality scoring: {}: {error}", photo.filename);
+                continue;
+            }
+        };
+        repo.store_photo_quality(&photo.id, &quality).await?;
+        scored += 1;
+    }
+    if scored > 0 {
+        println!("Quality scoring: scored {scored} photo(s)");
+    }
+    Ok(())
+}
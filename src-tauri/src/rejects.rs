@@ -0,0 +1,61 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+/// What happens to the original of a purged photo.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum PurgeDestination {
+    /// Only the library entry goes; the file stays where it is.
+    LibraryOnly,
+    Trash,
+    Folder { path: String },
+}
+
+/// Moves the original out of the way; returns where it ended up, if anywhere on disk.
+pub fn dispose_original(path: &str, destination: &PurgeDestination) -> Result<Option<PathBuf>, String> {
+    match destination {
+        PurgeDestination::LibraryOnly => Ok(None),
+        PurgeDestination::Trash => trash::delete(path)
+            .map(|()| None)
+            .map_err(|e| format!("Failed to move {path} to the trash: {e}")),
+        PurgeDestination::Folder { path: folder } => move_into_folder(Path::new(path), Path::new(folder)).map(Some),
+    }
+}
+
+fn move_into_folder(source: &Path, folder: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(folder)
+        .map_err(|e| format!("Failed to create rejects folder {}: {e}", folder.to_string_lossy()))?;
+    let target = unique_target(source, folder)?;
+    // Rename fails across volumes; fall back to copying and removing the original.
+    if std::fs::rename(source, &target).is_err() {
+        std::fs::copy(source, &target)
+            .map_err(|e| format!("Failed to copy {} to {}: {e}", source.to_string_lossy(), target.to_string_lossy()))?;
+        std::fs::remove_file(source)
+            .map_err(|e| format!("Failed to remove {}: {e}", source.to_string_lossy()))?;
+    }
+    Ok(target)
+}
+
+/// `name.jpg`, then `name (1).jpg`, `name (2).jpg`, ... so earlier rejects are never overwritten.
+fn unique_target(source: &Path, folder: &Path) -> Result<PathBuf, String> {
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", source.to_string_lossy()))?;
+    let candidate = folder.join(file_name);
+    if !candidate.exists() {
+        return Ok(candidate);
+    }
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = source
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| folder.join(format!("{stem} ({n}){extension}")))
+        .find(|candidate| !candidate.exists())
+        .ok_or_else(|| "No free file name in rejects folder".to_string())
+}
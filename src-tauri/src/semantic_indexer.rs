@@ -0,0 +1,98 @@
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::analysis_cache;
+use crate::ditto_repo::{DittoRepository, PhotoPayload};
+
+const CLIP_CACHE: &str = "clip";
+
+/// Embeds every photo in the library once per content ID. Works from the synced thumbnail,
+/// which is already larger than the model input, so photos from other peers are searchable too.
+/// With `auto_tagging`, photos nobody has tagged yet get machine tags from the same embedding.
+pub fn spawn(app: AppHandle) -> mpsc::UnboundedSender<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            while rx.try_recv().is_ok() {}
+            if let Err(error) = run_pass(&app).await {
+                eprintln!("Semantic indexing: {error}");
+            }
+        }
+    });
+    tx
+}
+
+async fn run_pass(app: &AppHandle) -> Result<(), String> {
+    if !crate::semantic_search::enabled() {
+        return Ok(());
+    }
+    let Some(repo) = app.try_state::<DittoRepository>() else {
+        return Ok(());
+    };
+    let (mut indexed, mut tagged) = (0, 0);
+    // Virtual copies look exactly like their source; indexing them would only duplicate results.
+    for photo in repo
+        .get_photos()
+        .await?
+        .into_iter()
+        .filter(|photo| photo.source_photo_id.is_none())
+    {
+        let embedding = match cached_embedding(&photo) {
+            Some(embedding) => embedding,
+            None => match embed_photo(photo.clone()).await {
+                Ok(embedding) => {
+                    indexed += 1;
+                    embedding
+                }
+                Err(error) => {
+                    eprintln!("Semantic indexing: {}: {error}", photo.filename);
+                    continue;
+                }
+            },
+        };
+        if crate::auto_tagging::enabled() && photo.machine_tags.is_none() {
+            let tags = match tauri::async_runtime::spawn_blocking(move || {
+                crate::auto_tagging::classify(&embedding)
+            })
+            .await
+            .map_err(|e| format!("Auto tagging task failed: {e}"))
+            .and_then(|result| result)
+            {
+                Ok(tags) => tags,
+                Err(error) => {
+                    eprintln!("Auto tagging: {}: {error}", photo.filename);
+                    continue;
+                }
+            };
+            if let Err(error) = repo.update_photo_machine_tags(&photo.id, &tags).await {
+                eprintln!("Auto tagging: {}: {error}", photo.filename);
+                continue;
+            }
+            tagged += 1;
+        }
+    }
+    if indexed > 0 {
+        println!("Semantic indexing: embedded {indexed} photo(s)");
+    }
+    if tagged > 0 {
+        println!("Auto tagging: tagged {tagged} photo(s)");
+    }
+    Ok(())
+}
+
+pub fn cached_embedding(photo: &PhotoPayload) -> Option<Vec<f32>> {
+    analysis_cache::get(CLIP_CACHE, &photo.id, &photo.image_path)
+}
+
+async fn embed_photo(photo: PhotoPayload) -> Result<Vec<f32>, String> {
+    let thumbnail = crate::thumbnails::decode_thumbnail(&photo.base64)
+        .ok_or_else(|| "Thumbnail could not be decoded".to_string())?;
+    let embedding = tauri::async_runtime::spawn_blocking(move || {
+        crate::semantic_search::embed_image(&thumbnail)
+    })
+    .await
+    .map_err(|e| format!("Semantic indexing task failed: {e}"))??;
+    analysis_cache::put(CLIP_CACHE, &photo.id, &photo.image_path, &embedding)?;
+    crate::similarity_index::add_clip_embedding(&photo.id, embedding.clone());
+    Ok(embedding)
+}
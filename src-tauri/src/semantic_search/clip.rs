@@ -0,0 +1,123 @@
+use std::sync::OnceLock;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use tokenizers::Tokenizer;
+use tract_onnx::prelude::*;
+
+/// CLIP ViT-B/32 split into its two towers, both projecting into the same 512-d space.
+const IMAGE_MODEL_FILE: &str = "clip-image.onnx";
+const TEXT_MODEL_FILE: &str = "clip-text.onnx";
+const TOKENIZER_FILE: &str = "clip-tokenizer.json";
+const INPUT_SIZE: u32 = 224;
+const CONTEXT_LENGTH: usize = 77;
+const PIXEL_MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const PIXEL_STD: [f32; 3] = [0.268_629_54, 0.261_302_58, 0.275_777_11];
+
+type ClipModel = TypedRunnableModel<TypedModel>;
+
+static IMAGE_MODEL: OnceLock<Result<ClipModel, String>> = OnceLock::new();
+static TEXT_MODEL: OnceLock<Result<ClipModel, String>> = OnceLock::new();
+static TOKENIZER: OnceLock<Result<Tokenizer, String>> = OnceLock::new();
+
+fn image_model() -> Result<&'static ClipModel, String> {
+    IMAGE_MODEL
+        .get_or_init(|| {
+            load_model(
+                IMAGE_MODEL_FILE,
+                f32::fact([1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize]).into(),
+            )
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+fn text_model() -> Result<&'static ClipModel, String> {
+    TEXT_MODEL
+        .get_or_init(|| load_model(TEXT_MODEL_FILE, i64::fact([1, CONTEXT_LENGTH]).into()))
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+fn tokenizer() -> Result<&'static Tokenizer, String> {
+    TOKENIZER
+        .get_or_init(|| {
+            let path = crate::models::model_path(TOKENIZER_FILE)?;
+            Tokenizer::from_file(&path).map_err(|e| format!("Failed to load CLIP tokenizer: {e}"))
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
+fn load_model(file_name: &str, input: InferenceFact) -> Result<ClipModel, String> {
+    let path = crate::models::model_path(file_name)?;
+    tract_onnx::onnx()
+        .model_for_path(&path)
+        .and_then(|model| model.with_input_fact(0, input))
+        .and_then(|model| model.into_optimized())
+        .and_then(|model| model.into_runnable())
+        .map_err(|e| format!("Failed to load {file_name}: {e}"))
+}
+
+pub fn embed_image(img: &DynamicImage) -> Result<Vec<f32>, String> {
+    let model = image_model()?;
+    let rgb = center_crop(img).to_rgb8();
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, INPUT_SIZE as usize, INPUT_SIZE as usize),
+        |(_, c, y, x)| {
+            (rgb.get_pixel(x as u32, y as u32)[c] as f32 / 255.0 - PIXEL_MEAN[c]) / PIXEL_STD[c]
+        },
+    )
+    .into();
+    run(model, input, "Image embedding")
+}
+
+pub fn embed_text(text: &str) -> Result<Vec<f32>, String> {
+    let model = text_model()?;
+    let encoding = tokenizer()?
+        .encode(text, true)
+        .map_err(|e| format!("Failed to tokenize query: {e}"))?;
+    // Longer queries are cut off, keeping the end-of-text token the text tower pools on.
+    let mut ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+    if ids.len() > CONTEXT_LENGTH {
+        let end_of_text = *ids.last().expect("encoding is not empty");
+        ids.truncate(CONTEXT_LENGTH);
+        ids[CONTEXT_LENGTH - 1] = end_of_text;
+    }
+    ids.resize(CONTEXT_LENGTH, 0);
+    let input: Tensor = tract_ndarray::Array2::from_shape_vec((1, CONTEXT_LENGTH), ids)
+        .map_err(|e| e.to_string())?
+        .into();
+    run(model, input, "Text embedding")
+}
+
+fn run(model: &ClipModel, input: Tensor, what: &str) -> Result<Vec<f32>, String> {
+    let outputs = model
+        .run(tvec!(input.into()))
+        .map_err(|e| format!("{what} failed: {e}"))?;
+    let embedding: Vec<f32> = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .copied()
+        .collect();
+    Ok(super::normalize(embedding))
+}
+
+/// Shortest side to the model size, then the centre square, as CLIP was trained.
+fn center_crop(img: &DynamicImage) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let scale = INPUT_SIZE as f32 / width.min(height).max(1) as f32;
+    let resized = img.resize_exact(
+        ((width as f32 * scale).round() as u32).max(INPUT_SIZE),
+        ((height as f32 * scale).round() as u32).max(INPUT_SIZE),
+        FilterType::Triangle,
+    );
+    let (width, height) = resized.dimensions();
+    resized.crop_imm(
+        (width - INPUT_SIZE) / 2,
+        (height - INPUT_SIZE) / 2,
+        INPUT_SIZE,
+        INPUT_SIZE,
+    )
+}
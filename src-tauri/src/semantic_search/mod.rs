@@ -0,0 +1,65 @@
+use image::DynamicImage;
+use serde::Serialize;
+
+#[cfg(feature = "semantic_search")]
+mod clip;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SemanticMatch {
+    pub id: String,
+    /// Cosine similarity between the query and the photo; CLIP scores rarely exceed 0.4.
+    pub score: f32,
+}
+
+pub const fn enabled() -> bool {
+    cfg!(feature = "semantic_search")
+}
+
+pub fn embed_image(img: &DynamicImage) -> Result<Vec<f32>, String> {
+    #[cfg(feature = "semantic_search")]
+    {
+        clip::embed_image(img)
+    }
+
+    #[cfg(not(feature = "semantic_search"))]
+    {
+        let _ = img;
+        Err("Semantic search is not enabled in this build".to_string())
+    }
+}
+
+pub fn embed_text(text: &str) -> Result<Vec<f32>, String> {
+    #[cfg(feature = "semantic_search")]
+    {
+        clip::embed_text(text)
+    }
+
+    #[cfg(not(feature = "semantic_search"))]
+    {
+        let _ = text;
+        Err("Semantic search is not enabled in this build".to_string())
+    }
+}
+
+/// Photos ordered by similarity to the query embedding, best first.
+pub fn rank(query: &[f32], photos: &[(String, Vec<f32>)], limit: usize) -> Vec<SemanticMatch> {
+    let mut matches: Vec<SemanticMatch> = photos
+        .iter()
+        .map(|(id, embedding)| SemanticMatch {
+            id: id.clone(),
+            score: crate::faces::cosine_similarity(query, embedding),
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg_attr(not(feature = "semantic_search"), allow(dead_code))]
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc;
+
+use crate::ditto_repo::{DittoRepository, PhotoPayload};
+use crate::perceptual_hash::photo_hash;
+use crate::vector_index::{Metric, VectorIndex};
+
+struct Indexes {
+    phash: VectorIndex,
+    clip: VectorIndex,
+}
+
+static INDEXES: OnceLock<Indexes> = OnceLock::new();
+
+/// ANN indexes over per-photo vectors, stored in `vector_index/` next to the Ditto root.
+pub fn init(app: &AppHandle) -> Result<(), String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
+        .join("vector_index");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create vector index dir: {e}"))?;
+    let _ = INDEXES.set(Indexes {
+        phash: VectorIndex::open(dir.join("phash.hnsw"), Metric::SquaredL2),
+        clip: VectorIndex::open(dir.join("clip.hnsw"), Metric::Cosine),
+    });
+    Ok(())
+}
+
+/// Brings the indexes in line with the library on every photos observer event. Photo IDs are
+/// content IDs, so only photos that are not indexed yet need their vectors computed.
+pub fn spawn(app: AppHandle) -> mpsc::UnboundedSender<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+    tauri::async_runtime::spawn(async move {
+        while rx.recv().await.is_some() {
+            while rx.try_recv().is_ok() {}
+            if let Err(error) = run_pass(&app).await {
+                eprintln!("Similarity index: {error}");
+            }
+        }
+    });
+    tx
+}
+
+async fn run_pass(app: &AppHandle) -> Result<(), String> {
+    let (Some(indexes), Some(repo)) = (INDEXES.get(), app.try_state::<DittoRepository>()) else {
+        return Ok(());
+    };
+    let photos: Vec<_> = repo
+        .get_photos()
+        .await?
+        .into_iter()
+        .filter(|photo| photo.source_photo_id.is_none())
+        .collect();
+    let live: HashSet<String> = photos.iter().map(|photo| photo.id.clone()).collect();
+
+    let mut phash_changed = indexes.phash.retain(&live);
+    let mut clip_changed = indexes.clip.retain(&live);
+    for photo in &photos {
+        if !indexes.phash.contains(&photo.id) {
+            if let Some(vector) = phash_vector(photo) {
+                phash_changed |= indexes.phash.upsert(&photo.id, vector);
+            }
+        }
+        if !indexes.clip.contains(&photo.id) {
+            if let Some(embedding) = crate::semantic_indexer::cached_embedding(photo) {
+                clip_changed |= indexes.clip.upsert(&photo.id, embedding);
+            }
+        }
+    }
+    if phash_changed {
+        indexes.phash.save()?;
+    }
+    if clip_changed {
+        indexes.clip.save()?;
+    }
+    Ok(())
+}
+
+/// The 64 pHash bits as 0/1 components, so squared L2 distance equals Hamming distance.
+fn phash_vector(photo: &PhotoPayload) -> Option<Vec<f32>> {
+    let bits = photo_hash(photo)?.phash_bits()?;
+    Some((0..64).map(|bit| ((bits >> bit) & 1) as f32).collect())
+}
+
+/// Called by the semantic indexer as embeddings are computed, so search sees them right away.
+pub fn add_clip_embedding(id: &str, embedding: Vec<f32>) {
+    if let Some(indexes) = INDEXES.get() {
+        if indexes.clip.upsert(id, embedding) {
+            if let Err(error) = indexes.clip.save() {
+                eprintln!("Similarity index: {error}");
+            }
+        }
+    }
+}
+
+/// Nearest photos by pHash as `(id, hamming distance)`; `None` until the index has been built.
+pub fn nearest_by_phash(photo: &PhotoPayload, k: usize) -> Option<Vec<(String, u32)>> {
+    let indexes = INDEXES.get().filter(|indexes| indexes.phash.len() > 0)?;
+    let results = indexes.phash.search(phash_vector(photo)?, k + 1);
+    Some(
+        results
+            .into_iter()
+            .filter(|(id, _)| *id != photo.id)
+            .map(|(id, distance)| (id, distance.round() as u32))
+            .collect(),
+    )
+}
+
+/// Nearest photos to a CLIP embedding as `(id, cosine similarity)`; `None` until the index has been built.
+pub fn nearest_by_clip(embedding: &[f32], k: usize) -> Option<Vec<(String, f32)>> {
+    let indexes = INDEXES.get().filter(|indexes| indexes.clip.len() > 0)?;
+    Some(
+        indexes
+            .clip
+            .search(embedding.to_vec(), k)
+            .into_iter()
+            .map(|(id, distance)| (id, 1.0 - distance))
+            .collect(),
+    )
+}
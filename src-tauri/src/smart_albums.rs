@@ -0,0 +1,155 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::ditto_repo::{ColorLabel, PhotoPayload};
+use crate::metadata::parse_capture_time;
+
+/// Every rule that is set must match; unset rules are ignored.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SmartAlbumRules {
+    #[serde(default)]
+    pub favorite: Option<bool>,
+    #[serde(default)]
+    pub min_rating: Option<u8>,
+    #[serde(default)]
+    pub max_rating: Option<u8>,
+    /// Matches photos carrying any of these labels.
+    #[serde(default)]
+    pub color_labels: Vec<ColorLabel>,
+    #[serde(default)]
+    pub rejected: Option<bool>,
+    #[serde(default)]
+    pub stack_primary: Option<bool>,
+    #[serde(default)]
+    pub author_peer_id: Option<String>,
+    #[serde(default)]
+    pub camera_model: Option<String>,
+    #[serde(default)]
+    pub captured_after: Option<String>,
+    #[serde(default)]
+    pub captured_before: Option<String>,
+    #[serde(default)]
+    pub filename_pattern: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub machine_tags: Vec<String>,
+    /// Applies to `machine_tags`; defaults to accepting whatever the classifier kept.
+    #[serde(default)]
+    pub min_machine_tag_confidence: Option<f32>,
+}
+
+impl SmartAlbumRules {
+    pub fn matches(&self, photo: &PhotoPayload) -> bool {
+        if let Some(favorite) = self.favorite {
+            if photo.favorite != favorite {
+                return false;
+            }
+        }
+        if self.min_rating.is_some_and(|min| photo.rating < min)
+            || self.max_rating.is_some_and(|max| photo.rating > max)
+        {
+            return false;
+        }
+        if !self.color_labels.is_empty()
+            && !self.color_labels.iter().any(|label| photo.color_labels.contains(label))
+        {
+            return false;
+        }
+        if self.rejected.is_some_and(|rejected| photo.rejected != rejected) {
+            return false;
+        }
+        if let Some(stack_primary) = self.stack_primary {
+            if photo.is_stack_primary != stack_primary {
+                return false;
+            }
+        }
+        if let Some(author) = self.author_peer_id.as_deref() {
+            if photo.author_peer_id.as_deref() != Some(author) {
+                return false;
+            }
+        }
+        if let Some(camera_model) = self.camera_model.as_deref() {
+            let model = photo.metadata.as_ref().and_then(|m| m.model.as_deref());
+            match model {
+                Some(model) if model.to_lowercase().contains(&camera_model.to_lowercase()) => {}
+                _ => return false,
+            }
+        }
+        if self.captured_after.is_some() || self.captured_before.is_some() {
+            let Some(captured) = photo
+                .metadata
+                .as_ref()
+                .and_then(|m| m.datetime.as_deref())
+                .and_then(parse_capture_time)
+            else {
+                return false;
+            };
+            if let Some(after) = self.captured_after.as_deref().and_then(|v| parse_range_bound(v, false)) {
+                if captured < after {
+                    return false;
+                }
+            }
+            if let Some(before) = self.captured_before.as_deref().and_then(|v| parse_range_bound(v, true)) {
+                if captured > before {
+                    return false;
+                }
+            }
+        }
+        if let Some(pattern) = self.filename_pattern.as_deref() {
+            if !wildcard_match(&pattern.to_lowercase(), &photo.filename.to_lowercase()) {
+                return false;
+            }
+        }
+        if !self.tags.iter().all(|tag| crate::tags::has_tag(&photo.tags, tag)) {
+            return false;
+        }
+        let min_confidence = self.min_machine_tag_confidence.unwrap_or(0.0);
+        self.machine_tags.iter().all(|wanted| {
+            photo
+                .machine_tags
+                .iter()
+                .flatten()
+                .any(|tag| tag.tag == *wanted && tag.confidence >= min_confidence)
+        })
+    }
+}
+
+/// Date-only bounds cover the whole day, so `captured_before: "2024-06-01"` includes that day.
+fn parse_range_bound(value: &str, end_of_day: bool) -> Option<NaiveDateTime> {
+    if let Some(datetime) = parse_capture_time(value) {
+        return Some(datetime);
+    }
+    let date = NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()?;
+    if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+}
+
+/// Matches `*` (any run of characters) and `?` (exactly one character).
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ditto_repo::PhotoPayload;
+use crate::metadata::parse_capture_time;
+use crate::perceptual_hash::photo_hash;
+use crate::quality::photo_quality;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StackSuggestion {
+    /// Derived from the member IDs, so the same group gets the same ID on every peer and run.
+    pub id: String,
+    pub photo_ids: Vec<String>,
+    pub primary_id: String,
+}
+
+/// Groups unstacked photos whose pHashes are chained within `threshold` bits of each other.
+pub fn suggest_stacks(photos: &[PhotoPayload], threshold: u32) -> Vec<StackSuggestion> {
+    let candidates: Vec<(&PhotoPayload, u64)> = photos
+        .iter()
+        .filter(|photo| photo.stack_id.is_none())
+        .filter_map(|photo| Some((photo, photo_hash(photo)?.phash_bits()?)))
+        .collect();
+
+    let mut parent: Vec<usize> = (0..candidates.len()).collect();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..candidates.len() {
+        for j in (i + 1)..candidates.len() {
+            if (candidates[i].1 ^ candidates[j].1).count_ones() <= threshold {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[b] = a;
+                }
+            }
+        }
+    }
+
+    let mut groups: std::collections::HashMap<usize, Vec<&PhotoPayload>> =
+        std::collections::HashMap::new();
+    for (idx, (photo, _)) in candidates.iter().enumerate() {
+        let root = find(&mut parent, idx);
+        groups.entry(root).or_default().push(photo);
+    }
+
+    let mut suggestions: Vec<StackSuggestion> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let primary_id = pick_primary(&members).id.clone();
+            let mut photo_ids: Vec<String> = members.iter().map(|photo| photo.id.clone()).collect();
+            photo_ids.sort();
+            StackSuggestion {
+                id: suggestion_id(&photo_ids),
+                photo_ids,
+                primary_id,
+            }
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.photo_ids.len().cmp(&a.photo_ids.len()).then(a.id.cmp(&b.id)));
+    suggestions
+}
+
+/// Favorites first, then the best quality score, then the earliest capture.
+fn pick_primary<'a>(members: &[&'a PhotoPayload]) -> &'a PhotoPayload {
+    let key = |photo: &PhotoPayload| {
+        let captured = photo
+            .metadata
+            .as_ref()
+            .and_then(|m| m.datetime.as_deref())
+            .and_then(parse_capture_time);
+        let quality = photo_quality(photo).map(|score| score.overall);
+        (!photo.favorite, quality, captured.is_none(), captured, photo.id.clone())
+    };
+    members
+        .iter()
+        .copied()
+        .map(|photo| (photo, key(photo)))
+        .min_by(|(_, a), (_, b)| {
+            a.0.cmp(&b.0)
+                .then_with(|| b.1.unwrap_or(-1.0).total_cmp(&a.1.unwrap_or(-1.0)))
+                .then_with(|| (a.2, a.3, &a.4).cmp(&(b.2, b.3, &b.4)))
+        })
+        .map(|(photo, _)| photo)
+        .expect("suggestions have at least two members")
+}
+
+pub fn suggestion_id(sorted_photo_ids: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for id in sorted_photo_ids {
+        hasher.update(id.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
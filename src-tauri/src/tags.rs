@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+/// Separates the levels of a hierarchical tag, e.g. `Event/Ceremony/Rings`.
+pub const TAG_SEPARATOR: char = '/';
+
+/// Trims every level and drops empty ones; `None` when nothing is left.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let levels: Vec<&str> = tag
+        .split(TAG_SEPARATOR)
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .collect();
+    (!levels.is_empty()).then(|| levels.join(&TAG_SEPARATOR.to_string()))
+}
+
+/// `tag` equals `ancestor` or sits somewhere below it.
+pub fn is_within(tag: &str, ancestor: &str) -> bool {
+    tag == ancestor
+        || tag
+            .strip_prefix(ancestor)
+            .is_some_and(|rest| rest.starts_with(TAG_SEPARATOR))
+}
+
+/// Tagging a photo `Event/Ceremony` also makes it match a search for `Event`.
+pub fn has_tag(tags: &[String], wanted: &str) -> bool {
+    tags.iter().any(|tag| is_within(tag, wanted))
+}
+
+/// Every level of the path: `A`, `A/B`, `A/B/C`.
+pub fn tag_ancestors(tag: &str) -> Vec<String> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(|(idx, _)| tag[..idx].to_string())
+        .chain(std::iter::once(tag.to_string()))
+        .collect()
+}
+
+/// Moves `tag` from under `from` to under `to`, keeping whatever sits below it.
+pub fn rename_tag(tag: &str, from: &str, to: &str) -> Option<String> {
+    if !is_within(tag, from) {
+        return None;
+    }
+    Some(format!("{to}{}", &tag[from.len()..]))
+}
+
+/// Sorted, without duplicates.
+pub fn dedup_tags(mut tags: Vec<String>) -> Vec<String> {
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Number of photos per tag node; a photo tagged `A/B` counts towards both `A` and `A/B`.
+pub fn count_tags<'a>(photo_tags: impl Iterator<Item = &'a [String]>) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for tags in photo_tags {
+        let nodes = dedup_tags(tags.iter().flat_map(|tag| tag_ancestors(tag)).collect());
+        for node in nodes {
+            *counts.entry(node).or_default() += 1;
+        }
+    }
+    counts
+}
@@ -0,0 +1,12 @@
+use base64::{engine::general_purpose, Engine as _};
+use image::DynamicImage;
+
+/// Decodes the `data:<mime>;base64,...` thumbnail stored on every photo document.
+pub fn decode_thumbnail(data_url: &str) -> Option<DynamicImage> {
+    let encoded = data_url
+        .split_once(";base64,")
+        .map(|(_, data)| data)
+        .unwrap_or(data_url);
+    let bytes = general_purpose::STANDARD.decode(encoded).ok()?;
+    image::load_from_memory(&bytes).ok()
+}
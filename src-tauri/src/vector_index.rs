@@ -0,0 +1,492 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+/// Links per node on the upper layers; layer 0 keeps twice as many.
+const MAX_CONNECTIONS: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+const MIN_EF_SEARCH: usize = 64;
+/// Bumped whenever the on-disk layout changes; older files are discarded and rebuilt.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Metric {
+    /// `1 - cos`; vectors are normalized on insert.
+    Cosine,
+    /// For 0/1 vectors this is the Hamming distance.
+    SquaredL2,
+}
+
+impl Metric {
+    fn distance(self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => 1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>(),
+            Metric::SquaredL2 => a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum(),
+        }
+    }
+
+    fn prepare(self, mut vector: Vec<f32>) -> Vec<f32> {
+        if self == Metric::Cosine {
+            let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if norm > 0.0 {
+                vector.iter_mut().for_each(|v| *v /= norm);
+            }
+        }
+        vector
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    vector: Vec<f32>,
+    /// Neighbour node indices per layer, layer 0 first.
+    links: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Hierarchical navigable small world graph (Malkov & Yashunin). Removals are tombstones;
+/// the graph is rebuilt once they make up a quarter of it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Hnsw {
+    version: u32,
+    metric: Metric,
+    nodes: Vec<Node>,
+    ids: HashMap<String, usize>,
+    entry_point: Option<usize>,
+    deleted: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hnsw {
+    fn new(metric: Metric) -> Self {
+        Hnsw {
+            version: FORMAT_VERSION,
+            metric,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry_point: None,
+            deleted: 0,
+        }
+    }
+
+    fn top_layer(&self) -> usize {
+        self.entry_point
+            .map(|entry| self.nodes[entry].links.len() - 1)
+            .unwrap_or(0)
+    }
+
+    fn distance_to(&self, query: &[f32], node: usize) -> f32 {
+        self.metric.distance(query, &self.nodes[node].vector)
+    }
+
+    /// Returns false when the vector was already indexed unchanged under `id`.
+    fn upsert(&mut self, id: &str, vector: Vec<f32>) -> bool {
+        let vector = self.metric.prepare(vector);
+        if let Some(&existing) = self.ids.get(id) {
+            if self.nodes[existing].vector == vector {
+                return false;
+            }
+            self.remove(id);
+        }
+        self.insert(id.to_string(), vector);
+        true
+    }
+
+    fn insert(&mut self, id: String, vector: Vec<f32>) {
+        let node = self.nodes.len();
+        let level = random_level(&id, node);
+        self.nodes.push(Node {
+            id: id.clone(),
+            vector,
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.ids.insert(id, node);
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            return;
+        };
+        let query = self.nodes[node].vector.clone();
+        let top = self.top_layer();
+        for layer in (level + 1..=top).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        let mut entries = vec![entry];
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &entries, EF_CONSTRUCTION, layer);
+            let max = max_connections(layer);
+            let neighbours: Vec<usize> = candidates.iter().take(max).map(|c| c.node).collect();
+            self.nodes[node].links[layer] = neighbours.clone();
+            for neighbour in neighbours {
+                self.nodes[neighbour].links[layer].push(node);
+                if self.nodes[neighbour].links[layer].len() > max {
+                    self.prune(neighbour, layer, max);
+                }
+            }
+            entries = candidates.into_iter().map(|c| c.node).collect();
+        }
+        if level > top {
+            self.entry_point = Some(node);
+        }
+    }
+
+    fn prune(&mut self, node: usize, layer: usize, max: usize) {
+        let vector = &self.nodes[node].vector;
+        let mut links: Vec<Candidate> = self.nodes[node].links[layer]
+            .iter()
+            .map(|&other| Candidate {
+                distance: self.metric.distance(vector, &self.nodes[other].vector),
+                node: other,
+            })
+            .collect();
+        links.sort();
+        links.truncate(max);
+        self.nodes[node].links[layer] = links.into_iter().map(|c| c.node).collect();
+    }
+
+    fn remove(&mut self, id: &str) -> bool {
+        let Some(node) = self.ids.remove(id) else {
+            return false;
+        };
+        self.nodes[node].deleted = true;
+        self.deleted += 1;
+        if self.deleted >= 64 && self.deleted * 4 >= self.nodes.len() {
+            self.rebuild();
+        }
+        true
+    }
+
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        *self = Hnsw::new(self.metric);
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(node.id, node.vector);
+        }
+    }
+
+    fn greedy_closest(&self, query: &[f32], mut current: usize, layer: usize) -> usize {
+        let mut best = self.distance_to(query, current);
+        loop {
+            let mut improved = false;
+            for &neighbour in &self.nodes[current].links[layer] {
+                let distance = self.distance_to(query, neighbour);
+                if distance < best {
+                    best = distance;
+                    current = neighbour;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return current;
+            }
+        }
+    }
+
+    /// Best-first search of one layer; returns up to `ef` nodes, closest first.
+    fn search_layer(&self, query: &[f32], entries: &[usize], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entries.iter().copied().collect();
+        let mut frontier: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &entry in entries {
+            let candidate = Candidate {
+                distance: self.distance_to(query, entry),
+                node: entry,
+            };
+            frontier.push(std::cmp::Reverse(candidate));
+            found.push(candidate);
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(std::cmp::Reverse(current)) = frontier.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|worst| current.distance > worst.distance) {
+                break;
+            }
+            let Some(links) = self.nodes[current.node].links.get(layer) else {
+                continue;
+            };
+            for &neighbour in links {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: self.distance_to(query, neighbour),
+                    node: neighbour,
+                };
+                if found.len() < ef || found.peek().is_some_and(|worst| candidate < *worst) {
+                    frontier.push(std::cmp::Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+        found.into_sorted_vec()
+    }
+
+    fn search(&self, query: Vec<f32>, k: usize) -> Vec<(String, f32)> {
+        let Some(mut entry) = self.entry_point else {
+            return Vec::new();
+        };
+        let query = self.metric.prepare(query);
+        for layer in (1..=self.top_layer()).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        // Tombstones still route the search, so ask for extra results to make up for them.
+        let ef = (k + self.deleted.min(k)).max(MIN_EF_SEARCH);
+        self.search_layer(&query, &[entry], ef, 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.node].deleted)
+            .take(k)
+            .map(|c| (self.nodes[c.node].id.clone(), c.distance))
+            .collect()
+    }
+}
+
+fn max_connections(layer: usize) -> usize {
+    if layer == 0 {
+        MAX_CONNECTIONS * 2
+    } else {
+        MAX_CONNECTIONS
+    }
+}
+
+/// Exponentially distributed layer, seeded from the ID so rebuilds produce the same graph.
+fn random_level(id: &str, salt: usize) -> usize {
+    let mut x = id
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64 ^ salt as u64, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^= x >> 31;
+    let uniform = ((x >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+    let level_multiplier = 1.0 / (MAX_CONNECTIONS as f64).ln();
+    ((-uniform.ln() * level_multiplier) as usize).min(16)
+}
+
+/// An HNSW index persisted to a single file.
+pub struct VectorIndex {
+    path: PathBuf,
+    inner: RwLock<Hnsw>,
+}
+
+impl VectorIndex {
+    /// Starts empty if the file is missing, unreadable or from an older format.
+    pub fn open(path: PathBuf, metric: Metric) -> Self {
+        let inner = std::fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<Hnsw>(&bytes).ok())
+            .filter(|index| index.version == FORMAT_VERSION && index.metric == metric)
+            .unwrap_or_else(|| Hnsw::new(metric));
+        VectorIndex {
+            path,
+            inner: RwLock::new(inner),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().map(|index| index.ids.len()).unwrap_or(0)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.inner
+            .read()
+            .map(|index| index.ids.contains_key(id))
+            .unwrap_or(false)
+    }
+
+    pub fn upsert(&self, id: &str, vector: Vec<f32>) -> bool {
+        self.inner
+            .write()
+            .map(|mut index| index.upsert(id, vector))
+            .unwrap_or(false)
+    }
+
+    /// Drops every ID that is not in `live`; returns whether anything was removed.
+    pub fn retain(&self, live: &HashSet<String>) -> bool {
+        let Ok(mut index) = self.inner.write() else {
+            return false;
+        };
+        let stale: Vec<String> = index
+            .ids
+            .keys()
+            .filter(|id| !live.contains(*id))
+            .cloned()
+            .collect();
+        for id in &stale {
+            index.remove(id);
+        }
+        !stale.is_empty()
+    }
+
+    /// Up to `k` nearest IDs with their distances, closest first.
+    pub fn search(&self, query: Vec<f32>, k: usize) -> Vec<(String, f32)> {
+        self.inner
+            .read()
+            .map(|index| index.search(query, k))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let bytes = {
+            let index = self
+                .inner
+                .read()
+                .map_err(|_| "Vector index lock poisoned".to_string())?;
+            bincode::serialize(&*index).map_err(|e| format!("Failed to encode vector index: {e}"))?
+        };
+        write_atomic(&self.path, &bytes)
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write vector index: {e}"))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write vector index: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic xorshift vectors, so failures reproduce.
+    fn random_vectors(count: usize, dims: usize, mut seed: u64) -> Vec<Vec<f32>> {
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+        };
+        (0..count)
+            .map(|_| (0..dims).map(|_| next()).collect())
+            .collect()
+    }
+
+    fn brute_force(index: &Hnsw, query: &[f32], k: usize) -> Vec<String> {
+        let query = index.metric.prepare(query.to_vec());
+        let mut all: Vec<(f32, &str)> = index
+            .nodes
+            .iter()
+            .filter(|node| !node.deleted)
+            .map(|node| (index.metric.distance(&query, &node.vector), node.id.as_str()))
+            .collect();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        all.into_iter().take(k).map(|(_, id)| id.to_string()).collect()
+    }
+
+    fn recall(index: &Hnsw, queries: &[Vec<f32>], k: usize) -> f32 {
+        let mut hits = 0;
+        for query in queries {
+            let expected: HashSet<String> = brute_force(index, query, k).into_iter().collect();
+            hits += index
+                .search(query.clone(), k)
+                .iter()
+                .filter(|(id, _)| expected.contains(id))
+                .count();
+        }
+        hits as f32 / (queries.len() * k) as f32
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("picksy-{name}-{}.hnsw", std::process::id()))
+    }
+
+    #[test]
+    fn search_recall_matches_brute_force() {
+        for metric in [Metric::Cosine, Metric::SquaredL2] {
+            let mut index = Hnsw::new(metric);
+            for (i, vector) in random_vectors(1000, 32, 7).into_iter().enumerate() {
+                index.upsert(&format!("photo-{i}"), vector);
+            }
+            let queries = random_vectors(50, 32, 99);
+            let recall = recall(&index, &queries, 10);
+            assert!(recall >= 0.9, "{metric:?} recall@10 was {recall}");
+        }
+    }
+
+    #[test]
+    fn search_skips_removed_ids_and_keeps_recall_after_rebuild() {
+        let mut index = Hnsw::new(Metric::Cosine);
+        for (i, vector) in random_vectors(1000, 16, 3).into_iter().enumerate() {
+            index.upsert(&format!("photo-{i}"), vector);
+        }
+        for i in (0..1000).step_by(3) {
+            assert!(index.remove(&format!("photo-{i}")));
+        }
+        let queries = random_vectors(30, 16, 11);
+        for query in &queries {
+            for (id, _) in index.search(query.clone(), 10) {
+                assert!(index.ids.contains_key(&id), "{id} was removed");
+            }
+        }
+        let recall = recall(&index, &queries, 10);
+        assert!(recall >= 0.9, "recall@10 after removals was {recall}");
+    }
+
+    #[test]
+    fn upsert_reports_unchanged_vectors() {
+        let mut index = Hnsw::new(Metric::SquaredL2);
+        assert!(index.upsert("a", vec![1.0, 0.0]));
+        assert!(!index.upsert("a", vec![1.0, 0.0]));
+        assert!(index.upsert("a", vec![0.0, 1.0]));
+        assert_eq!(index.search(vec![0.0, 1.0], 1), vec![("a".to_string(), 0.0)]);
+    }
+
+    #[test]
+    fn saved_index_reopens_with_the_same_results() {
+        let path = temp_path("round-trip");
+        let vectors = random_vectors(300, 16, 5);
+        let index = VectorIndex::open(path.clone(), Metric::Cosine);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.upsert(&format!("photo-{i}"), vector.clone());
+        }
+        let live: HashSet<String> = (0..300)
+            .filter(|i| i % 10 != 0)
+            .map(|i| format!("photo-{i}"))
+            .collect();
+        index.retain(&live);
+        index.save().unwrap();
+
+        let reopened = VectorIndex::open(path.clone(), Metric::Cosine);
+        assert_eq!(reopened.len(), live.len());
+        assert!(reopened.contains("photo-1"));
+        assert!(!reopened.contains("photo-10"));
+        for query in random_vectors(10, 16, 17) {
+            assert_eq!(reopened.search(query.clone(), 5), index.search(query, 5));
+        }
+
+        // A file written for another metric is not reused.
+        assert_eq!(VectorIndex::open(path.clone(), Metric::SquaredL2).len(), 0);
+        std::fs::remove_file(path).unwrap();
+    }
+}
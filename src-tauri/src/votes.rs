@@ -0,0 +1,205 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteChoice {
+    Pick,
+    Reject,
+}
+
+/// One peer's opinion on one photo.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PeerVote {
+    pub photo_id: String,
+    pub peer_key: String,
+    pub choice: Option<VoteChoice>,
+    pub rating: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteFilter {
+    /// Picked by every voting peer.
+    Consensus,
+    /// Picked by someone and rejected by someone else.
+    Conflict,
+    /// Voted on by this peer.
+    Mine,
+    /// Nobody has voted on it yet.
+    Unvoted,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VoteSummary {
+    pub photo_id: String,
+    /// "Picked by `picks` of `peer_count` peers".
+    pub picks: usize,
+    pub rejects: usize,
+    pub peer_count: usize,
+    pub average_rating: Option<f32>,
+    /// `(picks - rejects) / peer_count`, in `-1.0..=1.0`.
+    pub score: f32,
+    pub consensus: bool,
+    pub conflict: bool,
+    pub my_choice: Option<VoteChoice>,
+    pub my_rating: Option<u8>,
+    pub votes: Vec<PeerVote>,
+}
+
+impl VoteSummary {
+    pub fn matches(&self, filter: VoteFilter) -> bool {
+        match filter {
+            VoteFilter::Consensus => self.consensus,
+            VoteFilter::Conflict => self.conflict,
+            VoteFilter::Mine => self.my_choice.is_some() || self.my_rating.is_some(),
+            VoteFilter::Unvoted => self.votes.is_empty(),
+        }
+    }
+}
+
+/// Aggregates per photo. The electorate is every peer that has voted anywhere in the library,
+/// plus this one, so a photo nobody else looked at doesn't count as unanimous.
+pub fn summarize(photo_ids: &[String], votes: Vec<PeerVote>, local_peer_key: &str) -> Vec<VoteSummary> {
+    let mut voters: BTreeSet<&str> = votes.iter().map(|vote| vote.peer_key.as_str()).collect();
+    voters.insert(local_peer_key);
+    let peer_count = voters.len();
+
+    let mut by_photo: HashMap<String, Vec<PeerVote>> = HashMap::new();
+    for vote in votes.iter().filter(|vote| vote.choice.is_some() || vote.rating.is_some()) {
+        by_photo.entry(vote.photo_id.clone()).or_default().push(vote.clone());
+    }
+
+    photo_ids
+        .iter()
+        .map(|photo_id| {
+            let mut votes = by_photo.remove(photo_id).unwrap_or_default();
+            votes.sort_by(|a, b| a.peer_key.cmp(&b.peer_key));
+            let count = |choice: VoteChoice| votes.iter().filter(|vote| vote.choice == Some(choice)).count();
+            let (picks, rejects) = (count(VoteChoice::Pick), count(VoteChoice::Reject));
+            let ratings: Vec<u8> = votes.iter().filter_map(|vote| vote.rating).collect();
+            let mine = votes.iter().find(|vote| vote.peer_key == local_peer_key);
+            VoteSummary {
+                photo_id: photo_id.clone(),
+                picks,
+                rejects,
+                peer_count,
+                average_rating: (!ratings.is_empty())
+                    .then(|| ratings.iter().map(|r| *r as f32).sum::<f32>() / ratings.len() as f32),
+                score: (picks as f32 - rejects as f32) / peer_count as f32,
+                consensus: peer_count > 1 && picks == peer_count,
+                conflict: picks > 0 && rejects > 0,
+                my_choice: mine.and_then(|vote| vote.choice),
+                my_rating: mine.and_then(|vote| vote.rating),
+                votes,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote(photo_id: &str, peer_key: &str, choice: Option<VoteChoice>, rating: Option<u8>) -> PeerVote {
+        PeerVote {
+            photo_id: photo_id.to_string(),
+            peer_key: peer_key.to_string(),
+            choice,
+            rating,
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn consensus_needs_a_pick_from_every_voting_peer() {
+        let votes = vec![
+            vote("p1", "a", Some(VoteChoice::Pick), None),
+            vote("p1", "b", Some(VoteChoice::Pick), Some(4)),
+            vote("p2", "a", Some(VoteChoice::Pick), None),
+        ];
+        let summaries = summarize(&ids(&["p1", "p2"]), votes, "a");
+
+        assert_eq!(summaries[0].peer_count, 2);
+        assert!(summaries[0].matches(VoteFilter::Consensus));
+        assert!(!summaries[0].matches(VoteFilter::Conflict));
+        assert_eq!(summaries[0].score, 1.0);
+        assert_eq!(summaries[0].average_rating, Some(4.0));
+
+        // Only one of the two voters looked at p2.
+        assert!(!summaries[1].matches(VoteFilter::Consensus));
+        assert_eq!(summaries[1].picks, 1);
+        assert_eq!(summaries[1].score, 0.5);
+    }
+
+    #[test]
+    fn a_lone_peer_is_never_a_consensus() {
+        let summaries = summarize(&ids(&["p1"]), vec![vote("p1", "a", Some(VoteChoice::Pick), None)], "a");
+        assert_eq!(summaries[0].peer_count, 1);
+        assert!(!summaries[0].consensus);
+    }
+
+    #[test]
+    fn pick_and_reject_conflict() {
+        let votes = vec![
+            vote("p1", "a", Some(VoteChoice::Pick), None),
+            vote("p1", "b", Some(VoteChoice::Reject), None),
+            vote("p1", "c", Some(VoteChoice::Pick), None),
+        ];
+        let summary = &summarize(&ids(&["p1"]), votes, "a")[0];
+        assert!(summary.matches(VoteFilter::Conflict));
+        assert!(!summary.matches(VoteFilter::Consensus));
+        assert_eq!((summary.picks, summary.rejects), (2, 1));
+        assert!((summary.score - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn a_changed_vote_counts_as_its_latest_choice() {
+        // Votes are one document per photo and peer, so a change overwrites the earlier choice.
+        let before = vec![
+            vote("p1", "a", Some(VoteChoice::Pick), None),
+            vote("p1", "b", Some(VoteChoice::Pick), None),
+        ];
+        assert!(summarize(&ids(&["p1"]), before, "a")[0].consensus);
+
+        let changed = vec![
+            vote("p1", "a", Some(VoteChoice::Pick), None),
+            vote("p1", "b", Some(VoteChoice::Reject), None),
+        ];
+        let summary = &summarize(&ids(&["p1"]), changed, "a")[0];
+        assert!(!summary.consensus);
+        assert!(summary.conflict);
+
+        // Clearing the choice but keeping a rating leaves the peer voting, without a pick.
+        let cleared = vec![
+            vote("p1", "a", Some(VoteChoice::Pick), None),
+            vote("p1", "b", None, Some(2)),
+        ];
+        let summary = &summarize(&ids(&["p1"]), cleared, "b")[0];
+        assert_eq!((summary.picks, summary.rejects), (1, 0));
+        assert!(!summary.consensus && !summary.conflict);
+        assert_eq!(summary.my_choice, None);
+        assert_eq!(summary.my_rating, Some(2));
+        assert!(summary.matches(VoteFilter::Mine));
+    }
+
+    #[test]
+    fn cleared_votes_leave_a_photo_unvoted() {
+        let votes = vec![
+            vote("p1", "a", None, None),
+            vote("p2", "b", Some(VoteChoice::Reject), None),
+        ];
+        let summaries = summarize(&ids(&["p1", "p2"]), votes, "a");
+        assert!(summaries[0].matches(VoteFilter::Unvoted));
+        assert!(!summaries[0].matches(VoteFilter::Mine));
+        // A peer that voted anywhere still counts towards every photo's electorate.
+        assert_eq!(summaries[0].peer_count, 2);
+        assert!(!summaries[1].matches(VoteFilter::Unvoted));
+        assert!(!summaries[1].matches(VoteFilter::Mine));
+        assert_eq!(summaries[1].score, -0.5);
+    }
+}